@@ -1,24 +1,125 @@
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::fmt::{self, Debug, Display, Formatter};
 use std::io;
 use std::io::{BufRead, BufReader, ErrorKind, Read};
+use std::str::FromStr;
 use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant};
 
-pub trait IO {
-    fn get(&mut self) -> io::Result<i64>;
-    fn put(&mut self, val: i64) -> io::Result<()>;
+use self::extension::Extension;
+use self::history::History;
+use self::memory::Memory;
+use self::watch::{Access, Action, Hit, SelfModification, Watches};
+use self::word::Word;
+
+pub mod ascii;
+pub mod asm;
+pub mod cfg;
+pub mod console;
+pub mod debugger;
+pub mod disasm;
+pub mod extension;
+pub mod fuzz;
+pub mod history;
+pub mod memory;
+pub mod network;
+pub mod packet;
+pub mod profiler;
+pub mod record;
+pub mod scheduler;
+pub mod symbolic;
+pub mod watch;
+pub mod word;
+
+pub trait IO<W = i64> {
+    fn get(&mut self) -> io::Result<W>;
+    fn put(&mut self, val: W) -> io::Result<()>;
+
+    /// Called when the machine reads a value queued with `push_input`
+    /// instead of calling `get`.
+    fn queued(&mut self, _val: &W) -> io::Result<()> {
+        Ok(())
+    }
 }
 
+/// IO for machines fed with `push_input` only: outputs are discarded, and a
+/// machine reading past its queued inputs waits with
+/// `Status::WaitingForInput` rather than reading a value.
+#[derive(Clone)]
 pub struct NoIO {}
 
-impl IO for NoIO {
-    fn get(&mut self) -> io::Result<i64> {
-        Ok(0)
+impl<W> IO<W> for NoIO {
+    fn get(&mut self) -> io::Result<W> {
+        Err(io::Error::from(ErrorKind::WouldBlock))
     }
 
-    fn put(&mut self, _: i64) -> io::Result<()> {
+    fn put(&mut self, _: W) -> io::Result<()> {
         Ok(())
     }
 }
 
+/// Single-threaded IO reading inputs from a queue and collecting outputs into
+/// another one. Reading from an empty input queue fails with
+/// `FaultKind::InputExhausted`, after which more inputs can be pushed and the
+/// machine run again.
+#[derive(Clone, Debug, Default)]
+pub struct QueueIO<W = i64> {
+    pub input: VecDeque<W>,
+    pub output: VecDeque<W>,
+}
+
+impl<W: Clone> QueueIO<W> {
+    pub fn new(input: &[W]) -> Self {
+        let mut io = Self {
+            input: VecDeque::new(),
+            output: VecDeque::new(),
+        };
+        io.push_inputs(input);
+        io
+    }
+
+    /// Queue inputs after those not read yet.
+    pub fn push_inputs(&mut self, values: &[W]) {
+        self.input.extend(values.iter().cloned());
+    }
+
+    /// Remove and return all outputs produced so far.
+    pub fn drain_outputs(&mut self) -> Vec<W> {
+        self.output.drain(..).collect()
+    }
+}
+
+impl<W> IO<W> for QueueIO<W> {
+    fn get(&mut self) -> io::Result<W> {
+        self.input
+            .pop_front()
+            .ok_or_else(|| io::Error::from(ErrorKind::UnexpectedEof))
+    }
+
+    fn put(&mut self, val: W) -> io::Result<()> {
+        self.output.push_back(val);
+        Ok(())
+    }
+}
+
+/// Hook called by `IntCodeComputer` on every executed instruction.
+pub trait Tracer<W = i64> {
+    /// Called before an instruction is executed, with its operands resolved:
+    /// values for parameters, addresses for destinations.
+    fn instruction(&mut self, _ip: usize, _opcode: Opcode, _operands: &[W]) {}
+    fn write(&mut self, _address: usize, _value: &W) {}
+    fn input(&mut self, _value: &W) {}
+    fn output(&mut self, _value: &W) {}
+    /// Called before an extension instruction is executed, like `instruction`.
+    fn extension(&mut self, _ip: usize, _mnemonic: &str, _operands: &[W]) {}
+}
+
+#[derive(Clone)]
+pub struct NoTracer {}
+
+impl<W> Tracer<W> for NoTracer {}
+
 pub struct AsyncIO {
     tx: Sender<i64>,
     rx: Receiver<i64>,
@@ -47,49 +148,84 @@ impl IO for AsyncIO {
     }
 }
 
-pub struct Connector {
-    tx: Vec<Sender<i64>>,
-    rx: Receiver<i64>,
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FaultKind {
+    InvalidOpcode(i64),
+    InvalidParameterMode(i64),
+    NegativeAddress(i64),
+    AddressOutOfRange(usize),
+    ImmediateDestination,
+    ArithmeticOverflow,
+    WordOutOfRange,
+    InputExhausted,
 }
 
-impl Connector {
-    pub fn new(tx: Sender<i64>, rx: Receiver<i64>) -> Self {
-        Self { tx: vec![tx], rx }
+impl Display for FaultKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FaultKind::InvalidOpcode(n) => write!(f, "invalid opcode {}", n),
+            FaultKind::InvalidParameterMode(n) => write!(f, "invalid parameter mode {}", n),
+            FaultKind::NegativeAddress(n) => write!(f, "negative address {}", n),
+            FaultKind::AddressOutOfRange(n) => write!(f, "address {} out of range", n),
+            FaultKind::ImmediateDestination => write!(f, "immediate mode used for destination"),
+            FaultKind::ArithmeticOverflow => write!(f, "arithmetic overflow"),
+            FaultKind::WordOutOfRange => write!(f, "value too large for an address or instruction"),
+            FaultKind::InputExhausted => write!(f, "no more input available"),
+        }
     }
+}
 
-    pub fn multiplexed(tx: Vec<Sender<i64>>, rx: Receiver<i64>) -> Self {
-        Self { tx, rx }
-    }
+#[derive(Debug, Eq, PartialEq)]
+pub struct IntCodeError<W = i64> {
+    pub ip: usize,
+    pub instruction: W,
+    pub kind: FaultKind,
+}
 
-    pub fn run(&self) {
-        while let Ok(data) = self.rx.recv() {
-            for tx in self.tx.iter() {
-                let _ = tx.send(data);
-            }
-        }
+impl<W: Display> Display for IntCodeError<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at ip {} (instruction {})",
+            self.kind, self.ip, self.instruction
+        )
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
-enum ParameterMode {
+impl<W: Debug + Display> std::error::Error for IntCodeError<W> {}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParameterMode {
     Position,
     Immediate,
     Relative,
 }
 
-impl From<i64> for ParameterMode {
-    fn from(n: i64) -> Self {
+impl TryFrom<i64> for ParameterMode {
+    type Error = FaultKind;
+
+    fn try_from(n: i64) -> Result<Self, Self::Error> {
         match n {
-            0 => ParameterMode::Position,
-            1 => ParameterMode::Immediate,
-            2 => ParameterMode::Relative,
-            _ => panic!("invalid parameter mode"),
+            0 => Ok(ParameterMode::Position),
+            1 => Ok(ParameterMode::Immediate),
+            2 => Ok(ParameterMode::Relative),
+            _ => Err(FaultKind::InvalidParameterMode(n)),
         }
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
-enum Opcode {
+impl From<ParameterMode> for i64 {
+    fn from(mode: ParameterMode) -> Self {
+        match mode {
+            ParameterMode::Position => 0,
+            ParameterMode::Immediate => 1,
+            ParameterMode::Relative => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Opcode {
     Add,
     Mul,
     In,
@@ -102,53 +238,206 @@ enum Opcode {
     Halt,
 }
 
-impl From<i64> for Opcode {
-    fn from(n: i64) -> Self {
+impl TryFrom<i64> for Opcode {
+    type Error = FaultKind;
+
+    fn try_from(n: i64) -> Result<Self, Self::Error> {
         match n {
-            1 => Opcode::Add,
-            2 => Opcode::Mul,
-            3 => Opcode::In,
-            4 => Opcode::Out,
-            5 => Opcode::Jit,
-            6 => Opcode::Jif,
-            7 => Opcode::Lt,
-            8 => Opcode::Eq,
-            9 => Opcode::Arb,
-            99 => Opcode::Halt,
-            _ => panic!("Invalid opcode"),
+            1 => Ok(Opcode::Add),
+            2 => Ok(Opcode::Mul),
+            3 => Ok(Opcode::In),
+            4 => Ok(Opcode::Out),
+            5 => Ok(Opcode::Jit),
+            6 => Ok(Opcode::Jif),
+            7 => Ok(Opcode::Lt),
+            8 => Ok(Opcode::Eq),
+            9 => Ok(Opcode::Arb),
+            99 => Ok(Opcode::Halt),
+            _ => Err(FaultKind::InvalidOpcode(n)),
+        }
+    }
+}
+
+impl Opcode {
+    /// Number of parameters following the instruction.
+    pub fn arity(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => 3,
+            Opcode::Jit | Opcode::Jif => 2,
+            Opcode::In | Opcode::Out | Opcode::Arb => 1,
+            Opcode::Halt => 0,
+        }
+    }
+
+    /// Whether the last parameter is an address the instruction writes to.
+    pub fn has_dest(self) -> bool {
+        matches!(
+            self,
+            Opcode::Add | Opcode::Mul | Opcode::In | Opcode::Lt | Opcode::Eq
+        )
+    }
+}
+
+impl From<Opcode> for i64 {
+    fn from(opcode: Opcode) -> Self {
+        match opcode {
+            Opcode::Add => 1,
+            Opcode::Mul => 2,
+            Opcode::In => 3,
+            Opcode::Out => 4,
+            Opcode::Jit => 5,
+            Opcode::Jif => 6,
+            Opcode::Lt => 7,
+            Opcode::Eq => 8,
+            Opcode::Arb => 9,
+            Opcode::Halt => 99,
+        }
+    }
+}
+
+impl Display for Opcode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mnemonic = match self {
+            Opcode::Add => "add",
+            Opcode::Mul => "mul",
+            Opcode::In => "in",
+            Opcode::Out => "out",
+            Opcode::Jit => "jit",
+            Opcode::Jif => "jif",
+            Opcode::Lt => "lt",
+            Opcode::Eq => "eq",
+            Opcode::Arb => "arb",
+            Opcode::Halt => "halt",
+        };
+        write!(f, "{}", mnemonic)
+    }
+}
+
+impl FromStr for Opcode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "add" => Ok(Opcode::Add),
+            "mul" => Ok(Opcode::Mul),
+            "in" => Ok(Opcode::In),
+            "out" => Ok(Opcode::Out),
+            "jit" => Ok(Opcode::Jit),
+            "jif" => Ok(Opcode::Jif),
+            "lt" => Ok(Opcode::Lt),
+            "eq" => Ok(Opcode::Eq),
+            "arb" => Ok(Opcode::Arb),
+            "halt" => Ok(Opcode::Halt),
+            _ => Err(format!("Invalid mnemonic: {}", s)),
         }
     }
 }
 
-fn decode_instruction(instruction: i64) -> (Opcode, Vec<ParameterMode>) {
-    let opcode = instruction % 100;
-    let param_modes = vec![
-        ((instruction / 100) % 10).into(),
-        ((instruction / 1000) % 10).into(),
-        ((instruction / 10000) % 10).into(),
-    ];
+type Decoded = (Opcode, [ParameterMode; 3]);
+
+fn decode_instruction(instruction: i64) -> Result<Decoded, FaultKind> {
+    let opcode = Opcode::try_from(instruction % 100)?;
+    Ok((opcode, decode_modes(instruction)?))
+}
+
+fn decode_modes(instruction: i64) -> Result<[ParameterMode; 3], FaultKind> {
+    Ok([
+        ParameterMode::try_from((instruction / 100) % 10)?,
+        ParameterMode::try_from((instruction / 1000) % 10)?,
+        ParameterMode::try_from((instruction / 10000) % 10)?,
+    ])
+}
 
-    (opcode.into(), param_modes)
+fn encode_instruction(opcode: Opcode, parameter_modes: &[ParameterMode]) -> i64 {
+    parameter_modes
+        .iter()
+        .zip(&[100, 1000, 10000])
+        .map(|(&mode, factor)| i64::from(mode) * factor)
+        .sum::<i64>()
+        + i64::from(opcode)
 }
 
 pub fn parse_program<R: Read>(r: R) -> Vec<i64> {
+    parse_words(r)
+}
+
+/// Parse a program into words of any size, for values that do not fit an i64.
+pub fn parse_words<W: Word, R: Read>(r: R) -> Vec<W> {
     BufReader::new(r)
         .split(b',')
         .flatten()
         .flat_map(String::from_utf8)
-        .flat_map(|s| s.parse())
+        .flat_map(|s| s.trim().parse())
         .collect()
 }
 
-pub struct IntCodeComputer<T>
+#[derive(Debug, Eq, PartialEq)]
+pub enum Status<W = i64> {
+    Running,
+    Output(W),
+    WaitingForInput,
+    Halted,
+    /// The run was stopped by its `Budget`, the program can be resumed.
+    BudgetExhausted,
+    /// An instruction accessed a cell watched with `Action::Pause`.
+    Watchpoint(Hit<W>),
+}
+
+/// Number of instructions executed between two checks of the clock.
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+/// Limits on the instructions executed by `IntCodeComputer::run_with_budget`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Budget {
+    pub instructions: Option<u64>,
+    pub timeout: Option<Duration>,
+}
+
+impl Budget {
+    pub fn instructions(instructions: u64) -> Self {
+        Self {
+            instructions: Some(instructions),
+            timeout: None,
+        }
+    }
+
+    pub fn timeout(timeout: Duration) -> Self {
+        Self {
+            instructions: None,
+            timeout: Some(timeout),
+        }
+    }
+}
+
+/// Full state of a machine, apart from its IO.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Snapshot<W = i64> {
+    memory: Memory<W>,
+    ip: usize,
+    relative_base: i64,
+    halt: bool,
+    input: VecDeque<W>,
+}
+
+#[derive(Clone)]
+pub struct IntCodeComputer<T, U = NoTracer, W = i64>
 where
-    T: IO,
+    T: IO<W>,
+    U: Tracer<W>,
+    W: Word,
 {
-    pub program: Vec<i64>,
+    pub memory: Memory<W>,
     pub io: T,
+    pub tracer: U,
     ip: usize,
     relative_base: i64,
     halt: bool,
+    checked: bool,
+    input: VecDeque<W>,
+    decoded: Vec<Option<(i64, Decoded)>>,
+    extensions: HashMap<i64, Extension<W>>,
+    watches: Option<Box<Watches<W>>>,
+    history: Option<Box<History<W>>>,
 }
 
 impl<T> IntCodeComputer<T>
@@ -156,175 +445,588 @@ where
     T: IO,
 {
     pub fn new(program: Vec<i64>, io: T) -> Self {
+        Self::from_words(program, io)
+    }
+}
+
+impl<T, W> IntCodeComputer<T, NoTracer, W>
+where
+    T: IO<W>,
+    W: Word,
+{
+    /// Create a machine computing with words of type `W`, such as `i128` or
+    /// `BigInt`, instead of `i64`.
+    pub fn from_words(program: Vec<W>, io: T) -> Self {
         Self {
             ip: 0,
-            program,
+            memory: Memory::new(program),
             io,
+            tracer: NoTracer {},
             relative_base: 0,
             halt: false,
+            checked: false,
+            input: VecDeque::new(),
+            decoded: vec![],
+            extensions: HashMap::new(),
+            watches: None,
+            history: None,
+        }
+    }
+}
+
+impl<T, U, W> IntCodeComputer<T, U, W>
+where
+    T: IO<W>,
+    U: Tracer<W>,
+    W: Word,
+{
+    pub fn with_tracer<V: Tracer<W>>(self, tracer: V) -> IntCodeComputer<T, V, W> {
+        IntCodeComputer {
+            memory: self.memory,
+            io: self.io,
+            tracer,
+            ip: self.ip,
+            relative_base: self.relative_base,
+            halt: self.halt,
+            checked: self.checked,
+            input: self.input,
+            decoded: self.decoded,
+            extensions: self.extensions,
+            watches: self.watches,
+            history: self.history,
+        }
+    }
+
+    /// Queue a value to be consumed by the next input instructions,
+    /// before falling back to the IO.
+    pub fn push_input(&mut self, val: W) {
+        self.input.push_back(val);
+    }
+
+    /// Make additions and multiplications whose result does not fit a word
+    /// fail with an error instead of wrapping around.
+    pub fn set_checked_arithmetic(&mut self, checked: bool) {
+        self.checked = checked;
+    }
+
+    /// Make `opcode` execute `extension`, replacing any extension previously
+    /// registered for it.
+    ///
+    /// Panics if `opcode` is one of the standard instructions, or does not
+    /// fit the two digits of an instruction.
+    pub fn register(&mut self, opcode: i64, extension: Extension<W>) {
+        assert!(
+            (1..100).contains(&opcode) && Opcode::try_from(opcode).is_err(),
+            "opcode {} cannot be used by an extension",
+            opcode
+        );
+        self.extensions.insert(opcode, extension);
+    }
+
+    /// Take `action` whenever an instruction makes the given access to the
+    /// cell at `address`.
+    pub fn watch(&mut self, address: usize, access: Access, action: Action<W>) {
+        self.watches().watch(address, access, action);
+    }
+
+    pub fn unwatch(&mut self, address: usize, access: Access) {
+        self.watches().unwatch(address, access);
+    }
+
+    /// Log writes into cells which were previously executed as part of an
+    /// instruction, see `self_modifications`.
+    pub fn detect_self_modification(&mut self, enabled: bool) {
+        self.watches().detect_self_modification(enabled);
+    }
+
+    pub fn self_modifications(&self) -> &[SelfModification<W>] {
+        self.watches
+            .as_ref()
+            .map_or(&[], |watches| &watches.modifications)
+    }
+
+    fn watches(&mut self) -> &mut Watches<W> {
+        self.watches.get_or_insert_with(Default::default)
+    }
+
+    /// Keep an undo log of the last `capacity` instructions executed, to step
+    /// back through them with `step_back`. Any previous log is dropped.
+    ///
+    /// Outputs cannot be taken back, and changes made between steps, like
+    /// values written by a debugger, are not undone.
+    pub fn set_history(&mut self, capacity: Option<usize>) {
+        self.history = capacity.map(|capacity| Box::new(History::new(capacity)));
+    }
+
+    /// Undo the last `steps` instructions, giving back the inputs they
+    /// consumed so that they are read again. Returns whether the log held
+    /// enough instructions, or the fault restoring a cell which can no longer
+    /// be written, such as one past a lowered memory limit.
+    pub fn step_back(&mut self, steps: usize) -> Result<bool, FaultKind> {
+        for _ in 0..steps {
+            let entry = match self.history.as_mut().and_then(|history| history.pop()) {
+                Some(entry) => entry,
+                None => return Ok(false),
+            };
+            for (idx, value) in entry.writes.into_iter().rev() {
+                self.memory.set(idx, value)?;
+            }
+            self.ip = entry.ip;
+            self.relative_base = entry.relative_base;
+            self.halt = false;
+            if let Some(value) = entry.input {
+                self.input.push_front(value);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Step back to the instruction which last wrote to `address`, and return
+    /// the number of instructions undone, or `None` if no instruction in the
+    /// log wrote to it.
+    pub fn rewind_to_write(&mut self, address: usize) -> Result<Option<usize>, FaultKind> {
+        let steps = match &self.history {
+            Some(history) => history.since_write(address),
+            None => None,
+        };
+        match steps {
+            Some(steps) => self.step_back(steps).map(|_| Some(steps)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot<W> {
+        Snapshot {
+            memory: self.memory.clone(),
+            ip: self.ip,
+            relative_base: self.relative_base,
+            halt: self.halt,
+            input: self.input.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot<W>) {
+        self.memory.clone_from(&snapshot.memory);
+        self.ip = snapshot.ip;
+        self.relative_base = snapshot.relative_base;
+        self.halt = snapshot.halt;
+        self.input.clone_from(&snapshot.input);
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+    }
+
+    /// Run until the program halts, or until it waits for an input that is
+    /// neither queued nor available from the IO, or a watchpoint pauses it,
+    /// and return which of these happened.
+    pub fn run(&mut self) -> Result<Status<W>, IntCodeError<W>> {
+        loop {
+            match self.step()? {
+                Status::Running | Status::Output(_) => {}
+                status => return Ok(status),
+            }
         }
     }
 
-    pub fn run(&mut self) {
-        while !self.halt {
-            self.step();
+    /// Run like `run`, but stop with `Status::BudgetExhausted` once the
+    /// budget is spent, leaving the machine ready to be run again.
+    pub fn run_with_budget(&mut self, budget: Budget) -> Result<Status<W>, IntCodeError<W>> {
+        let deadline = budget.timeout.map(|timeout| Instant::now() + timeout);
+        let mut executed = 0;
+        loop {
+            if budget.instructions.is_some_and(|max| executed >= max) {
+                return Ok(Status::BudgetExhausted);
+            }
+            if executed % DEADLINE_CHECK_INTERVAL == 0
+                && deadline.is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                return Ok(Status::BudgetExhausted);
+            }
+            match self.step()? {
+                Status::Running | Status::Output(_) => executed += 1,
+                status => return Ok(status),
+            }
         }
     }
 
-    pub fn step(&mut self) {
-        let (opcode, pms) = decode_instruction(self.read_memory(self.ip));
+    /// Run until the program halts, outputs a value, or waits for an input.
+    pub fn resume(&mut self) -> Result<Status<W>, IntCodeError<W>> {
+        loop {
+            match self.step()? {
+                Status::Running => {}
+                status => return Ok(status),
+            }
+        }
+    }
+
+    pub fn step(&mut self) -> Result<Status<W>, IntCodeError<W>> {
+        if self.halt {
+            return Ok(Status::Halted);
+        }
+        if self.history.is_some() || self.watches.is_some() {
+            return self.step_instrumented();
+        }
+        let instruction = self.read_memory(self.ip);
+        match self.execute(&instruction) {
+            Ok(status) => Ok(status),
+            Err(kind) => self.fault(instruction, kind),
+        }
+    }
+
+    /// Step while recording history or watching memory, kept out of `step`
+    /// so that machines without them do not pay for the bookkeeping.
+    #[cold]
+    #[inline(never)]
+    fn step_instrumented(&mut self) -> Result<Status<W>, IntCodeError<W>> {
+        if let Some(hit) = self.watches.as_mut().and_then(|w| w.paused.take()) {
+            return Ok(Status::Watchpoint(hit));
+        }
+        if let Some(history) = &mut self.history {
+            history.begin(self.ip, self.relative_base);
+        }
+        let instruction = self.read_memory(self.ip);
+        let status = match self.execute(&instruction) {
+            Ok(status) => Ok(status),
+            Err(kind) => self.fault(instruction, kind),
+        };
+        if let Some(history) = &mut self.history {
+            history.end(&status);
+        }
+
+        match &mut self.watches {
+            Some(watches) => watches.pause(status),
+            None => status,
+        }
+    }
+
+    fn execute(&mut self, instruction: &W) -> Result<Status<W>, FaultKind> {
+        let instruction = instruction.to_i64().ok_or(FaultKind::WordOutOfRange)?;
+        let (opcode, pms) = self.decode(instruction)?;
+        let operands = self.operands(opcode.arity(), opcode.has_dest(), &pms)?;
+        if self.watches.is_some() {
+            self.watch_instruction(opcode.arity(), opcode.has_dest(), &pms);
+        }
+        let o = &operands[..opcode.arity()];
+
+        // input instructions are only traced once they have a value to consume
+        if opcode != Opcode::In {
+            self.tracer.instruction(self.ip, opcode, o);
+        }
+
         match opcode {
-            Opcode::Add => self.add(&pms),
-            Opcode::Mul => self.mul(&pms),
-            Opcode::In => self.input(&pms),
-            Opcode::Out => self.output(&pms),
-            Opcode::Jit => self.jump_if_true(&pms),
-            Opcode::Jif => self.jump_if_false(&pms),
-            Opcode::Lt => self.less_than(&pms),
-            Opcode::Eq => self.equals(&pms),
-            Opcode::Arb => self.adjust_relative_base(&pms),
+            Opcode::Add => self.add(o),
+            Opcode::Mul => self.mul(o),
+            Opcode::In => self.input(o),
+            Opcode::Out => self.output(o),
+            Opcode::Jit => self.jump_if_true(o),
+            Opcode::Jif => self.jump_if_false(o),
+            Opcode::Lt => self.less_than(o),
+            Opcode::Eq => self.equals(o),
+            Opcode::Arb => self.adjust_relative_base(o),
             Opcode::Halt => self.halt(),
         }
     }
 
-    fn write_memory(&mut self, idx: i64, val: i64) {
-        if idx < 0 {
-            panic!("negative index");
+    /// Handle an instruction `execute` failed on: run it if it is a
+    /// registered extension, which `execute` did not decode, or report the
+    /// fault. Extensions are only looked up once decoding failed, before
+    /// anything was read or written, so that programs using none do not pay
+    /// for them.
+    #[cold]
+    #[inline(never)]
+    fn fault(&mut self, instruction: W, kind: FaultKind) -> Result<Status<W>, IntCodeError<W>> {
+        let status = match (kind, instruction.to_i64()) {
+            (FaultKind::InvalidOpcode(n), Some(word)) if self.extensions.contains_key(&n) => {
+                self.extension(n, word)
+            }
+            (kind, _) => Err(kind),
+        };
+        status.map_err(|kind| IntCodeError {
+            ip: self.ip,
+            instruction,
+            kind,
+        })
+    }
+
+    fn extension(&mut self, opcode: i64, instruction: i64) -> Result<Status<W>, FaultKind> {
+        let extension = self.extensions[&opcode].clone();
+        let pms = decode_modes(instruction)?;
+        let operands = self.operands(extension.arity, extension.has_dest, &pms)?;
+        if self.watches.is_some() {
+            self.watch_instruction(extension.arity, extension.has_dest, &pms);
+        }
+        let o = &operands[..extension.arity];
+        self.tracer.extension(self.ip, &extension.mnemonic, o);
+
+        let value = extension.call(o)?;
+        if let (true, Some(value)) = (extension.has_dest, value) {
+            self.write_memory(&o[extension.arity - 1], value)?;
+        }
+        self.ip += extension.arity + 1;
+        Ok(Status::Running)
+    }
+
+    /// Record the instruction at ip as executed, and the cells its
+    /// parameters read, once its operands are resolved.
+    #[cold]
+    fn watch_instruction(&mut self, arity: usize, has_dest: bool, pms: &[ParameterMode]) {
+        let reads = (0..arity - has_dest as usize)
+            .filter_map(|idx| {
+                let param = self.read_memory(self.ip + idx + 1);
+                match pms[idx] {
+                    ParameterMode::Position => address(&param).ok(),
+                    ParameterMode::Immediate => None,
+                    ParameterMode::Relative => address(&self.relative(&param).ok()?).ok(),
+                }
+            })
+            .map(|idx| (idx, self.read_memory(idx)))
+            .collect::<Vec<_>>();
+
+        if let Some(watches) = &mut self.watches {
+            watches.execute(self.ip, arity + 1);
+            for (idx, value) in reads {
+                watches.read(self.ip, idx, &value);
+            }
+        }
+    }
+
+    /// Decode the instruction at ip, reusing the result of a previous decoding
+    /// of the same address. Cached entries remember the instruction they were
+    /// decoded from, so they are invalidated by any write to code.
+    fn decode(&mut self, instruction: i64) -> Result<Decoded, FaultKind> {
+        if let Some(Some((cached, decoded))) = self.decoded.get(self.ip) {
+            if *cached == instruction {
+                return Ok(*decoded);
+            }
+        }
+
+        let decoded = decode_instruction(instruction)?;
+        if self.ip >= self.memory.as_slice().len() {
+            return Ok(decoded);
         }
-        let idx = idx as usize;
-        if idx >= self.program.len() {
-            self.program.resize(idx + 1, 0);
+        if self.ip >= self.decoded.len() {
+            self.decoded.resize(self.ip + 1, None);
         }
-        self.program[idx] = val;
+        self.decoded[self.ip] = Some((instruction, decoded));
+        Ok(decoded)
     }
 
-    fn read_memory(&self, idx: usize) -> i64 {
-        if idx >= self.program.len() {
-            0
-        } else {
-            self.program[idx]
+    fn write_memory(&mut self, idx: &W, val: W) -> Result<(), FaultKind> {
+        let idx = address(idx)?;
+        if self.history.is_some() || self.watches.is_some() {
+            return self.write_memory_instrumented(idx, val);
         }
+        self.memory.set(idx, val.clone())?;
+        self.tracer.write(idx, &val);
+        Ok(())
     }
 
-    fn parameter(&self, idx: usize, parameter_modes: &[ParameterMode]) -> i64 {
-        let param = self.read_memory(self.ip + idx + 1);
-        match parameter_modes[idx] {
-            ParameterMode::Position => self.read_memory(param as usize),
-            ParameterMode::Immediate => param,
-            ParameterMode::Relative => self.read_memory((param + self.relative_base) as usize),
+    /// Write while recording history or watching memory, see
+    /// `step_instrumented`.
+    #[cold]
+    #[inline(never)]
+    fn write_memory_instrumented(&mut self, idx: usize, val: W) -> Result<(), FaultKind> {
+        let old = self.history.as_ref().map(|_| self.memory.get(idx));
+        self.memory.set(idx, val.clone())?;
+        if let (Some(history), Some(old)) = (&mut self.history, old) {
+            history.write(idx, old);
         }
+        self.tracer.write(idx, &val);
+        if let Some(watches) = &mut self.watches {
+            watches.write(self.ip, idx, &val);
+        }
+        Ok(())
     }
 
-    fn dest(&self, idx: usize, parameter_modes: &[ParameterMode]) -> i64 {
+    fn read_memory(&self, idx: usize) -> W {
+        self.memory.get(idx)
+    }
+
+    fn parameter(&self, idx: usize, parameter_modes: &[ParameterMode]) -> Result<W, FaultKind> {
+        let param = self.read_memory(self.ip + idx + 1);
+        let idx = match parameter_modes[idx] {
+            ParameterMode::Position => address(&param)?,
+            ParameterMode::Immediate => return Ok(param),
+            ParameterMode::Relative => address(&self.relative(&param)?)?,
+        };
+        Ok(self.read_memory(idx))
+    }
+
+    fn dest(&self, idx: usize, parameter_modes: &[ParameterMode]) -> Result<W, FaultKind> {
         let dest = self.read_memory(self.ip + idx + 1);
         match parameter_modes[idx] {
-            ParameterMode::Position => dest,
-            ParameterMode::Immediate => panic!("invalid mode for dest"),
-            ParameterMode::Relative => dest + self.relative_base,
+            ParameterMode::Position => Ok(dest),
+            ParameterMode::Immediate => Err(FaultKind::ImmediateDestination),
+            ParameterMode::Relative => self.relative(&dest),
         }
     }
 
-    fn operands3(&self, parameter_modes: &[ParameterMode]) -> (i64, i64, i64) {
-        let param1 = self.parameter(0, parameter_modes);
-        let param2 = self.parameter(1, parameter_modes);
-        let dest = self.dest(2, parameter_modes);
-        (param1, param2, dest)
+    fn relative(&self, param: &W) -> Result<W, FaultKind> {
+        param
+            .checked_add(&W::from(self.relative_base))
+            .ok_or(FaultKind::WordOutOfRange)
     }
 
-    fn operands2(&self, parameter_modes: &[ParameterMode]) -> (i64, i64) {
-        let param1 = self.parameter(0, parameter_modes);
-        let param2 = self.parameter(1, parameter_modes);
-        (param1, param2)
+    /// Resolve the operands of an instruction: values for parameters,
+    /// addresses for destinations.
+    fn operands(
+        &self,
+        arity: usize,
+        has_dest: bool,
+        parameter_modes: &[ParameterMode],
+    ) -> Result<[W; 3], FaultKind> {
+        let mut operands = [W::zero(), W::zero(), W::zero()];
+        for (idx, operand) in operands.iter_mut().enumerate().take(arity) {
+            *operand = if has_dest && idx == arity - 1 {
+                self.dest(idx, parameter_modes)?
+            } else {
+                self.parameter(idx, parameter_modes)?
+            };
+        }
+        Ok(operands)
     }
 
-    fn add(&mut self, parameter_modes: &[ParameterMode]) {
-        let (o1, o2, d) = self.operands3(parameter_modes);
-        self.write_memory(d, o1 + o2);
+    fn add(&mut self, o: &[W]) -> Result<Status<W>, FaultKind> {
+        let sum = if self.checked {
+            o[0].checked_add(&o[1])
+                .ok_or(FaultKind::ArithmeticOverflow)?
+        } else {
+            o[0].wrapping_add(&o[1])
+        };
+        self.write_memory(&o[2], sum)?;
         self.ip += 4;
+        Ok(Status::Running)
     }
 
-    fn mul(&mut self, parameter_modes: &[ParameterMode]) {
-        let (o1, o2, d) = self.operands3(parameter_modes);
-        self.write_memory(d, o1 * o2);
+    fn mul(&mut self, o: &[W]) -> Result<Status<W>, FaultKind> {
+        let product = if self.checked {
+            o[0].checked_mul(&o[1])
+                .ok_or(FaultKind::ArithmeticOverflow)?
+        } else {
+            o[0].wrapping_mul(&o[1])
+        };
+        self.write_memory(&o[2], product)?;
         self.ip += 4;
+        Ok(Status::Running)
     }
 
-    fn input(&mut self, parameter_modes: &[ParameterMode]) {
-        let dest = self.dest(0, parameter_modes);
-        if let Ok(value) = self.io.get() {
-            self.write_memory(dest, value);
-            self.ip += 2;
-        } else {
-            self.halt = true;
+    fn input(&mut self, o: &[W]) -> Result<Status<W>, FaultKind> {
+        let value = match self.input.pop_front() {
+            Some(value) => self.io.queued(&value).map(|_| value),
+            None => self.io.get(),
+        };
+        match value {
+            Ok(value) => {
+                self.tracer.instruction(self.ip, Opcode::In, o);
+                self.tracer.input(&value);
+                if let Some(history) = &mut self.history {
+                    history.input(value.clone());
+                }
+                self.write_memory(&o[0], value)?;
+                self.ip += 2;
+                Ok(Status::Running)
+            }
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => Ok(Status::WaitingForInput),
+            Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => Err(FaultKind::InputExhausted),
+            Err(_) => self.halt(),
         }
     }
 
-    fn output(&mut self, parameter_modes: &[ParameterMode]) {
-        let param = self.parameter(0, parameter_modes);
-        if self.io.put(param).is_ok() {
+    fn output(&mut self, o: &[W]) -> Result<Status<W>, FaultKind> {
+        if self.io.put(o[0].clone()).is_ok() {
+            self.tracer.output(&o[0]);
             self.ip += 2;
+            Ok(Status::Output(o[0].clone()))
         } else {
-            self.halt = true;
+            self.halt()
         }
     }
 
-    fn jump_if_true(&mut self, parameter_modes: &[ParameterMode]) {
-        let (o1, o2) = self.operands2(parameter_modes);
-        self.ip = if o1 != 0 { o2 as usize } else { self.ip + 3 };
+    fn jump_if_true(&mut self, o: &[W]) -> Result<Status<W>, FaultKind> {
+        self.ip = if !o[0].is_zero() {
+            address(&o[1])?
+        } else {
+            self.ip + 3
+        };
+        Ok(Status::Running)
     }
 
-    fn jump_if_false(&mut self, parameter_modes: &[ParameterMode]) {
-        let (o1, o2) = self.operands2(parameter_modes);
-        self.ip = if o1 == 0 { o2 as usize } else { self.ip + 3 };
+    fn jump_if_false(&mut self, o: &[W]) -> Result<Status<W>, FaultKind> {
+        self.ip = if o[0].is_zero() {
+            address(&o[1])?
+        } else {
+            self.ip + 3
+        };
+        Ok(Status::Running)
     }
 
-    fn less_than(&mut self, parameter_modes: &[ParameterMode]) {
-        let (o1, o2, d) = self.operands3(parameter_modes);
-        self.write_memory(d, if o1 < o2 { 1 } else { 0 });
+    fn less_than(&mut self, o: &[W]) -> Result<Status<W>, FaultKind> {
+        let value = if o[0] < o[1] { W::one() } else { W::zero() };
+        self.write_memory(&o[2], value)?;
         self.ip += 4;
+        Ok(Status::Running)
     }
 
-    fn equals(&mut self, parameter_modes: &[ParameterMode]) {
-        let (o1, o2, d) = self.operands3(parameter_modes);
-        self.write_memory(d, if o1 == o2 { 1 } else { 0 });
+    fn equals(&mut self, o: &[W]) -> Result<Status<W>, FaultKind> {
+        let value = if o[0] == o[1] { W::one() } else { W::zero() };
+        self.write_memory(&o[2], value)?;
         self.ip += 4;
+        Ok(Status::Running)
     }
 
-    fn adjust_relative_base(&mut self, parameter_modes: &[ParameterMode]) {
-        let param = self.parameter(0, parameter_modes);
-        self.relative_base += param;
+    fn adjust_relative_base(&mut self, o: &[W]) -> Result<Status<W>, FaultKind> {
+        self.relative_base = o[0]
+            .to_i64()
+            .and_then(|offset| self.relative_base.checked_add(offset))
+            .ok_or(FaultKind::WordOutOfRange)?;
         self.ip += 2;
+        Ok(Status::Running)
     }
 
-    fn halt(&mut self) {
+    fn halt(&mut self) -> Result<Status<W>, FaultKind> {
         self.halt = true;
+        Ok(Status::Halted)
+    }
+}
+
+fn address<W: Word>(idx: &W) -> Result<usize, FaultKind> {
+    match idx.to_i64() {
+        Some(idx) if idx < 0 => Err(FaultKind::NegativeAddress(idx)),
+        Some(idx) => Ok(idx as usize),
+        None => Err(FaultKind::WordOutOfRange),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use num::BigInt;
 
     #[test]
     fn test_decode_instruction() {
         assert_eq!(
             decode_instruction(1002),
-            (
+            Ok((
                 Opcode::Mul,
-                vec![
+                [
                     ParameterMode::Position,
                     ParameterMode::Immediate,
                     ParameterMode::Position
                 ]
-            )
+            ))
+        );
+        assert_eq!(decode_instruction(42), Err(FaultKind::InvalidOpcode(42)));
+        assert_eq!(
+            decode_instruction(302),
+            Err(FaultKind::InvalidParameterMode(3))
         );
     }
 
     fn assert_program_output_eq(program: Vec<i64>, output: Vec<i64>) {
         let mut computer = IntCodeComputer::new(program, NoIO {});
 
-        computer.run();
-        assert_eq!(computer.program, output);
+        computer.run().unwrap();
+        assert_eq!(computer.memory.as_slice(), &output[..]);
     }
 
     #[test]
@@ -341,4 +1043,259 @@ mod tests {
         );
         assert_program_output_eq(vec![1101, 100, -1, 4, 0], vec![1101, 100, -1, 4, 99]);
     }
+
+    fn assert_program_error_eq(program: Vec<i64>, error: IntCodeError) {
+        let mut computer = IntCodeComputer::new(program, NoIO {});
+
+        assert_eq!(computer.run(), Err(error));
+    }
+
+    #[test]
+    fn test_run_program_errors() {
+        assert_program_error_eq(
+            vec![1, 0, 0, 0, 42],
+            IntCodeError {
+                ip: 4,
+                instruction: 42,
+                kind: FaultKind::InvalidOpcode(42),
+            },
+        );
+        assert_program_error_eq(
+            vec![1101, 1, 1, -1, 99],
+            IntCodeError {
+                ip: 0,
+                instruction: 1101,
+                kind: FaultKind::NegativeAddress(-1),
+            },
+        );
+        assert_program_error_eq(
+            vec![11101, 1, 1, 0, 99],
+            IntCodeError {
+                ip: 0,
+                instruction: 11101,
+                kind: FaultKind::ImmediateDestination,
+            },
+        );
+    }
+
+    #[test]
+    fn test_resume() {
+        // double every input forever
+        let program = vec![3, 11, 1002, 11, 2, 11, 4, 11, 1105, 1, 0, 0];
+        let mut computer = IntCodeComputer::new(program, NoIO {});
+        assert_eq!(computer.resume(), Ok(Status::WaitingForInput));
+        computer.push_input(21);
+        computer.push_input(5);
+        assert_eq!(computer.resume(), Ok(Status::Output(42)));
+        assert_eq!(computer.resume(), Ok(Status::Output(10)));
+        assert_eq!(computer.resume(), Ok(Status::WaitingForInput));
+
+        let mut computer = IntCodeComputer::new(vec![104, 7, 99], NoIO {});
+        assert_eq!(computer.resume(), Ok(Status::Output(7)));
+        assert_eq!(computer.resume(), Ok(Status::Halted));
+        assert_eq!(computer.resume(), Ok(Status::Halted));
+    }
+
+    #[test]
+    fn test_run_status() {
+        // NoIO has no input to give, so run stops without halting
+        let program = vec![3, 7, 4, 7, 1105, 1, 0, 0];
+        let mut computer = IntCodeComputer::new(program, NoIO {});
+        assert_eq!(computer.run(), Ok(Status::WaitingForInput));
+        computer.push_input(1);
+        assert_eq!(computer.run(), Ok(Status::WaitingForInput));
+
+        let mut computer = IntCodeComputer::new(vec![104, 7, 99], NoIO {});
+        assert_eq!(computer.run(), Ok(Status::Halted));
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        // output a counter which is incremented by each input
+        let program = vec![109, 100, 3, 13, 1, 13, 14, 14, 4, 14, 1105, 1, 2, 0, 0];
+        let mut computer = IntCodeComputer::new(program, NoIO {});
+        computer.push_input(1);
+        assert_eq!(computer.resume(), Ok(Status::Output(1)));
+
+        let snapshot = computer.snapshot();
+        computer.push_input(10);
+        assert_eq!(computer.resume(), Ok(Status::Output(11)));
+
+        computer.restore(&snapshot);
+        assert_eq!(computer.snapshot(), snapshot);
+        computer.push_input(5);
+        assert_eq!(computer.resume(), Ok(Status::Output(6)));
+
+        let mut fork = computer.clone();
+        fork.push_input(2);
+        assert_eq!(fork.resume(), Ok(Status::Output(8)));
+        assert_eq!(computer.resume(), Ok(Status::WaitingForInput));
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+    }
+
+    impl Tracer for Recorder {
+        fn instruction(&mut self, ip: usize, opcode: Opcode, operands: &[i64]) {
+            self.events
+                .push(format!("{} {} {:?}", ip, opcode, operands));
+        }
+
+        fn write(&mut self, address: usize, value: &i64) {
+            self.events.push(format!("[{}] <- {}", address, value));
+        }
+
+        fn input(&mut self, value: &i64) {
+            self.events.push(format!("in {}", value));
+        }
+
+        fn output(&mut self, value: &i64) {
+            self.events.push(format!("out {}", value));
+        }
+    }
+
+    #[test]
+    fn test_tracer() {
+        let program = vec![3, 9, 1001, 9, 1, 9, 4, 9, 99, 0];
+        let mut computer = IntCodeComputer::new(program, NoIO {}).with_tracer(Recorder::default());
+        assert_eq!(computer.run(), Ok(Status::WaitingForInput));
+        computer.push_input(41);
+        assert_eq!(computer.run(), Ok(Status::Halted));
+        assert_eq!(
+            computer.tracer.events,
+            vec![
+                "0 in [9]",
+                "in 41",
+                "[9] <- 41",
+                "2 add [41, 1, 9]",
+                "[9] <- 42",
+                "6 out [42]",
+                "out 42",
+                "8 halt []",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_self_modifying_code() {
+        // out #1, then overwrite it with halt and jump back to it
+        let program = vec![104, 1, 1101, 99, 0, 0, 1105, 1, 0];
+        let mut computer = IntCodeComputer::new(program, NoIO {});
+        assert_eq!(computer.resume(), Ok(Status::Output(1)));
+        assert_eq!(computer.resume(), Ok(Status::Halted));
+        assert_eq!(computer.memory[0], 99);
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        let program = vec![1101, 1, 1, 1_000_000_000_000, 99];
+        let mut computer = IntCodeComputer::new(program.clone(), NoIO {});
+        assert_eq!(computer.run(), Ok(Status::Halted));
+        assert_eq!(computer.memory[1_000_000_000_000], 2);
+
+        let mut computer = IntCodeComputer::new(program, NoIO {});
+        computer.memory.set_limit(Some(10_000));
+        assert_eq!(
+            computer.run(),
+            Err(IntCodeError {
+                ip: 0,
+                instruction: 1101,
+                kind: FaultKind::AddressOutOfRange(1_000_000_000_000),
+            })
+        );
+    }
+
+    #[test]
+    fn test_word_types() {
+        // squares 10^18, which overflows an i64
+        let program = "1102,1000000000000000000,1000000000000000000,7,4,7,99,0";
+        let square = "1000000000000000000000000000000000000";
+
+        let mut computer = IntCodeComputer::new(parse_program(program.as_bytes()), NoIO {});
+        assert_eq!(
+            computer.resume(),
+            Ok(Status::Output(
+                1_000_000_000_000_000_000i64.wrapping_mul(1_000_000_000_000_000_000)
+            ))
+        );
+
+        let mut computer =
+            IntCodeComputer::from_words(parse_words::<i128, _>(program.as_bytes()), NoIO {});
+        assert_eq!(
+            computer.resume(),
+            Ok(Status::Output(square.parse().unwrap()))
+        );
+
+        let mut computer =
+            IntCodeComputer::from_words(parse_words::<BigInt, _>(program.as_bytes()), NoIO {});
+        assert_eq!(
+            computer.resume(),
+            Ok(Status::Output(square.parse().unwrap()))
+        );
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let program = vec![1101, 1, 1, 9, 1101, i64::MAX, 1, 9, 99, 0];
+
+        let mut computer = IntCodeComputer::new(program.clone(), NoIO {});
+        computer.set_checked_arithmetic(true);
+        assert_eq!(
+            computer.run(),
+            Err(IntCodeError {
+                ip: 4,
+                instruction: 1101,
+                kind: FaultKind::ArithmeticOverflow,
+            })
+        );
+        assert_eq!(computer.memory[9], 2);
+
+        let mut computer = IntCodeComputer::new(program, NoIO {});
+        assert_eq!(computer.run(), Ok(Status::Halted));
+        assert_eq!(computer.memory[9], i64::MIN);
+    }
+
+    #[test]
+    fn test_run_with_budget() {
+        // counts down from 100 in [11], then halts
+        let program = vec![1001, 11, -1, 11, 1005, 11, 0, 4, 11, 99, 0, 100];
+        let mut computer = IntCodeComputer::new(program, NoIO {});
+        assert_eq!(
+            computer.run_with_budget(Budget::instructions(50)),
+            Ok(Status::BudgetExhausted)
+        );
+        assert_eq!(computer.memory[11], 75);
+        assert_eq!(
+            computer.run_with_budget(Budget::instructions(1000)),
+            Ok(Status::Halted)
+        );
+        assert_eq!(computer.memory[11], 0);
+
+        let mut computer = IntCodeComputer::new(vec![1105, 1, 0], NoIO {});
+        assert_eq!(
+            computer.run_with_budget(Budget::timeout(Duration::from_millis(10))),
+            Ok(Status::BudgetExhausted)
+        );
+    }
+
+    #[test]
+    fn test_queue_io() {
+        // outputs twice its input, until the input is exhausted
+        let program = vec![3, 9, 1002, 9, 2, 9, 4, 9, 1105, 1, 0];
+        let mut computer = IntCodeComputer::new(program, QueueIO::new(&[1, 2]));
+        let error = IntCodeError {
+            ip: 0,
+            instruction: 3,
+            kind: FaultKind::InputExhausted,
+        };
+        assert_eq!(computer.run(), Err(error));
+        assert_eq!(computer.io.drain_outputs(), vec![2, 4]);
+        assert_eq!(computer.io.drain_outputs(), vec![]);
+
+        computer.io.push_inputs(&[21]);
+        assert_eq!(computer.resume(), Ok(Status::Output(42)));
+        assert_eq!(computer.io.drain_outputs(), vec![42]);
+    }
 }
@@ -0,0 +1,87 @@
+use super::IO;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::io::{self, ErrorKind};
+
+/// IO for programs speaking ASCII: input is given as lines of text, output is
+/// decoded into lines, and values outside the ASCII range, such as a final
+/// result, are kept apart.
+#[derive(Clone, Debug, Default)]
+pub struct AsciiIO {
+    input: VecDeque<i64>,
+    line: String,
+    lines: VecDeque<String>,
+    values: VecDeque<i64>,
+}
+
+impl AsciiIO {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a line of input, terminated by a newline.
+    pub fn push_line(&mut self, line: &str) {
+        self.input.extend(line.bytes().map(i64::from));
+        self.input.push_back(i64::from(b'\n'));
+    }
+
+    /// Next complete line of output, without its newline.
+    pub fn next_line(&mut self) -> Option<String> {
+        self.lines.pop_front()
+    }
+
+    /// Remove and return all complete lines of output.
+    pub fn drain_lines(&mut self) -> Vec<String> {
+        self.lines.drain(..).collect()
+    }
+
+    /// Next output value that is not an ASCII character.
+    pub fn next_value(&mut self) -> Option<i64> {
+        self.values.pop_front()
+    }
+}
+
+impl IO for AsciiIO {
+    fn get(&mut self) -> io::Result<i64> {
+        self.input
+            .pop_front()
+            .ok_or_else(|| io::Error::from(ErrorKind::UnexpectedEof))
+    }
+
+    fn put(&mut self, val: i64) -> io::Result<()> {
+        match u8::try_from(val) {
+            Ok(b'\n') => {
+                let line = std::mem::take(&mut self.line);
+                self.lines.push_back(line);
+            }
+            Ok(c) if c.is_ascii() => self.line.push(char::from(c)),
+            _ => self.values.push_back(val),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{FaultKind, IntCodeComputer};
+
+    #[test]
+    fn test_ascii_io() {
+        // prints "hi" and 1000, then echoes its input
+        let program = vec![
+            104, 104, 104, 105, 104, 10, 104, 1000, 3, 20, 4, 20, 1105, 1, 8,
+        ];
+        let mut io = AsciiIO::new();
+        io.push_line("ok");
+        let mut computer = IntCodeComputer::new(program, io);
+
+        let error = computer.run().unwrap_err();
+        assert_eq!(error.kind, FaultKind::InputExhausted);
+        assert_eq!(computer.io.next_line(), Some("hi".to_string()));
+        assert_eq!(computer.io.drain_lines(), vec!["ok".to_string()]);
+        assert_eq!(computer.io.next_line(), None);
+        assert_eq!(computer.io.next_value(), Some(1000));
+        assert_eq!(computer.io.next_value(), None);
+    }
+}
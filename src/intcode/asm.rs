@@ -0,0 +1,319 @@
+use super::{encode_instruction, Opcode, ParameterMode};
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum AsmErrorKind {
+    UnknownMnemonic(String),
+    InvalidOperand(String),
+    InvalidLabel(String),
+    OperandCount { expected: usize, found: usize },
+    ImmediateDestination,
+    DuplicateLabel(String),
+    UndefinedLabel(String),
+}
+
+impl Display for AsmErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AsmErrorKind::UnknownMnemonic(s) => write!(f, "unknown mnemonic {}", s),
+            AsmErrorKind::InvalidOperand(s) => write!(f, "invalid operand {}", s),
+            AsmErrorKind::InvalidLabel(s) => write!(f, "invalid label {}", s),
+            AsmErrorKind::OperandCount { expected, found } => {
+                write!(f, "expected {} operands, found {}", expected, found)
+            }
+            AsmErrorKind::ImmediateDestination => write!(f, "immediate mode used for destination"),
+            AsmErrorKind::DuplicateLabel(s) => write!(f, "duplicate label {}", s),
+            AsmErrorKind::UndefinedLabel(s) => write!(f, "undefined label {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub kind: AsmErrorKind,
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+enum Value {
+    Number(i64),
+    Label(String),
+}
+
+enum Statement {
+    Instruction(Opcode, Vec<(ParameterMode, Value)>),
+    Data(Vec<Value>),
+}
+
+impl Statement {
+    fn size(&self) -> usize {
+        match self {
+            Statement::Instruction(_, operands) => operands.len() + 1,
+            Statement::Data(values) => values.len(),
+        }
+    }
+}
+
+/// Assemble a program written with the mnemonics of the disassembler.
+///
+/// Each line holds an optional `label:`, then either an instruction such as
+/// `add #1, [rb-2], [total]` or a `data 1, 2, label` directive. Anything after
+/// a `;` is a comment. Leading `0042:` addresses, as printed in disassembly
+/// listings, are ignored.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut statements = vec![];
+    let mut address = 0;
+
+    for (idx, line) in source.lines().enumerate() {
+        let error = |kind| AsmError {
+            line: idx + 1,
+            kind,
+        };
+
+        let mut line = line.split(';').next().unwrap_or("").trim();
+        while let Some(colon) = line.find(':') {
+            let label = line[..colon].trim();
+            line = line[colon + 1..].trim();
+            if label.parse::<usize>().is_ok() {
+                continue;
+            }
+            if !is_label(label) {
+                return Err(error(AsmErrorKind::InvalidLabel(label.to_string())));
+            }
+            if labels.insert(label.to_string(), address).is_some() {
+                return Err(error(AsmErrorKind::DuplicateLabel(label.to_string())));
+            }
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        let statement = parse_statement(line).map_err(error)?;
+        address += statement.size();
+        statements.push((idx + 1, statement));
+    }
+
+    let mut program = Vec::with_capacity(address);
+    for (line, statement) in statements {
+        let resolve = |value: &Value| match value {
+            Value::Number(n) => Ok(*n),
+            Value::Label(label) => labels.get(label).map(|&a| a as i64).ok_or(AsmError {
+                line,
+                kind: AsmErrorKind::UndefinedLabel(label.clone()),
+            }),
+        };
+
+        match statement {
+            Statement::Instruction(opcode, operands) => {
+                let modes = operands.iter().map(|(mode, _)| *mode).collect::<Vec<_>>();
+                program.push(encode_instruction(opcode, &modes));
+                for (_, value) in operands.iter() {
+                    program.push(resolve(value)?);
+                }
+            }
+            Statement::Data(values) => {
+                for value in values.iter() {
+                    program.push(resolve(value)?);
+                }
+            }
+        }
+    }
+
+    Ok(program)
+}
+
+fn parse_statement(line: &str) -> Result<Statement, AsmErrorKind> {
+    let (mnemonic, rest) = match line.find(char::is_whitespace) {
+        Some(idx) => (&line[..idx], line[idx..].trim()),
+        None => (line, ""),
+    };
+    let operands = if rest.is_empty() {
+        vec![]
+    } else {
+        rest.split(',').map(str::trim).collect::<Vec<_>>()
+    };
+
+    if mnemonic == "data" {
+        return Ok(Statement::Data(
+            operands
+                .into_iter()
+                .map(parse_value)
+                .collect::<Result<_, _>>()?,
+        ));
+    }
+
+    let opcode = mnemonic
+        .parse::<Opcode>()
+        .map_err(|_| AsmErrorKind::UnknownMnemonic(mnemonic.to_string()))?;
+    if operands.len() != opcode.arity() {
+        return Err(AsmErrorKind::OperandCount {
+            expected: opcode.arity(),
+            found: operands.len(),
+        });
+    }
+
+    let operands = operands
+        .into_iter()
+        .map(parse_operand)
+        .collect::<Result<Vec<_>, _>>()?;
+    if opcode.has_dest() && operands.last().map(|(mode, _)| *mode) == Some(ParameterMode::Immediate)
+    {
+        return Err(AsmErrorKind::ImmediateDestination);
+    }
+
+    Ok(Statement::Instruction(opcode, operands))
+}
+
+fn parse_operand(operand: &str) -> Result<(ParameterMode, Value), AsmErrorKind> {
+    let invalid = || AsmErrorKind::InvalidOperand(operand.to_string());
+
+    if let Some(value) = operand.strip_prefix('#') {
+        return Ok((ParameterMode::Immediate, parse_value(value)?));
+    }
+
+    let inner = operand
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .map(str::trim)
+        .ok_or_else(invalid)?;
+
+    if let Some(offset) = inner.strip_prefix("rb").map(str::trim) {
+        if offset.is_empty() {
+            return Ok((ParameterMode::Relative, Value::Number(0)));
+        }
+        if let Some(offset) = offset.strip_prefix('+') {
+            return Ok((ParameterMode::Relative, parse_value(offset.trim())?));
+        }
+        if let Some(offset) = offset.strip_prefix('-') {
+            // the magnitude of i64::MIN does not fit an i64
+            return offset
+                .trim()
+                .parse::<u64>()
+                .ok()
+                .and_then(|n| 0i64.checked_sub_unsigned(n))
+                .map(|n| (ParameterMode::Relative, Value::Number(n)))
+                .ok_or_else(invalid);
+        }
+    }
+
+    Ok((ParameterMode::Position, parse_value(inner)?))
+}
+
+fn parse_value(value: &str) -> Result<Value, AsmErrorKind> {
+    if let Ok(n) = value.parse() {
+        Ok(Value::Number(n))
+    } else if is_label(value) {
+        Ok(Value::Label(value.to_string()))
+    } else {
+        Err(AsmErrorKind::InvalidOperand(value.to_string()))
+    }
+}
+
+fn is_label(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::disasm::disassemble;
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            ; count down from 3
+                    in   [counter]
+            loop:   out  [counter]
+                    add  [counter], #-1, [counter]
+                    jit  [counter], #loop
+                    arb  #2
+                    out  [rb-1]
+                    halt
+            counter: data 3
+        ";
+        assert_eq!(
+            assemble(source),
+            Ok(vec![
+                3, 16, 4, 16, 1001, 16, -1, 16, 1005, 16, 2, 109, 2, 204, -1, 99, 3
+            ])
+        );
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        assert_eq!(
+            assemble("add #1, #2\nhalt"),
+            Err(AsmError {
+                line: 1,
+                kind: AsmErrorKind::OperandCount {
+                    expected: 3,
+                    found: 2
+                }
+            })
+        );
+        assert_eq!(
+            assemble("halt\njit #1, #nowhere"),
+            Err(AsmError {
+                line: 2,
+                kind: AsmErrorKind::UndefinedLabel(String::from("nowhere"))
+            })
+        );
+        assert_eq!(
+            assemble("out [rb-9223372036854775809]"),
+            Err(AsmError {
+                line: 1,
+                kind: AsmErrorKind::InvalidOperand(String::from("[rb-9223372036854775809]"))
+            })
+        );
+        assert_eq!(
+            assemble("in #3"),
+            Err(AsmError {
+                line: 1,
+                kind: AsmErrorKind::ImmediateDestination
+            })
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let program = vec![
+            109,
+            1,
+            204,
+            -1,
+            1001,
+            100,
+            1,
+            100,
+            1008,
+            100,
+            16,
+            101,
+            1006,
+            101,
+            0,
+            204,
+            i64::MIN,
+            99,
+            -1,
+            42,
+            7,
+        ];
+        let listing = disassemble(&program)
+            .iter()
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(assemble(&listing), Ok(program));
+    }
+}
@@ -0,0 +1,177 @@
+use super::disasm::{disassemble_at, Item, Line, Operand};
+use super::Opcode;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+/// Sequence of instructions only entered at its first one and only left
+/// after its last one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Block {
+    pub start: usize,
+    pub lines: Vec<Line>,
+    /// Start of the blocks execution may continue with.
+    pub successors: Vec<usize>,
+    /// Whether the block ends with a jump whose target is only known at run time.
+    pub indirect: bool,
+}
+
+/// Control-flow graph of the code reachable from address 0.
+///
+/// Like the disassembler, the analysis only sees the program as given: code
+/// written at run time, or reached through jumps to computed addresses, is
+/// not part of the graph.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Cfg {
+    pub blocks: BTreeMap<usize, Block>,
+}
+
+impl Cfg {
+    pub fn build(program: &[i64]) -> Self {
+        let mut lines = BTreeMap::new();
+        let mut leaders = BTreeSet::new();
+        leaders.insert(0);
+
+        let mut todo = vec![0];
+        while let Some(address) = todo.pop() {
+            if lines.contains_key(&address) {
+                continue;
+            }
+            let line = disassemble_at(program, address);
+            let (targets, _) = flow(&line);
+            if ends_block(&line) {
+                leaders.extend(&targets);
+            }
+            todo.extend(targets);
+            lines.insert(address, line);
+        }
+
+        let mut blocks = BTreeMap::new();
+        for &start in &leaders {
+            let mut block_lines = vec![];
+            let mut address = start;
+            loop {
+                let line = lines[&address].clone();
+                let (successors, indirect) = flow(&line);
+                let end = ends_block(&line);
+                address = line.address + line.item.size();
+                block_lines.push(line);
+
+                if end || leaders.contains(&address) {
+                    let block = Block {
+                        start,
+                        lines: block_lines,
+                        successors,
+                        indirect,
+                    };
+                    blocks.insert(start, block);
+                    break;
+                }
+            }
+        }
+
+        Self { blocks }
+    }
+
+    /// Write the graph in the Graphviz DOT format.
+    pub fn write_dot<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "digraph cfg {{")?;
+        writeln!(w, "    node [shape=box, fontname=\"monospace\"];")?;
+        for block in self.blocks.values() {
+            let label = block
+                .lines
+                .iter()
+                .map(|line| format!("{}\\l", line))
+                .collect::<String>();
+            writeln!(w, "    b{} [label=\"{}\"];", block.start, label)?;
+            for successor in &block.successors {
+                writeln!(w, "    b{} -> b{};", block.start, successor)?;
+            }
+            if block.indirect {
+                writeln!(w, "    b{} -> indirect;", block.start)?;
+            }
+        }
+        if self.blocks.values().any(|block| block.indirect) {
+            writeln!(w, "    indirect [label=\"?\", shape=ellipse];")?;
+        }
+        writeln!(w, "}}")
+    }
+}
+
+/// Whether execution cannot simply continue with the next instruction.
+fn ends_block(line: &Line) -> bool {
+    matches!(
+        line.item,
+        Item::Data(_)
+            | Item::Instruction(Opcode::Jit, _)
+            | Item::Instruction(Opcode::Jif, _)
+            | Item::Instruction(Opcode::Halt, _)
+    )
+}
+
+/// Addresses where execution may continue after an instruction, and whether
+/// it may also continue at an address computed at run time.
+fn flow(line: &Line) -> (Vec<usize>, bool) {
+    let next = line.address + line.item.size();
+    match &line.item {
+        Item::Instruction(opcode @ Opcode::Jit, operands)
+        | Item::Instruction(opcode @ Opcode::Jif, operands) => {
+            let taken = match operands[0] {
+                Operand::Immediate(v) => Some((v != 0) == (*opcode == Opcode::Jit)),
+                _ => None,
+            };
+            let target = match operands[1] {
+                Operand::Immediate(t) if t >= 0 => Some(t as usize),
+                _ => None,
+            };
+
+            let mut targets = vec![];
+            if taken != Some(false) {
+                targets.extend(target);
+            }
+            if taken != Some(true) {
+                targets.push(next);
+            }
+            (targets, taken != Some(false) && target.is_none())
+        }
+        Item::Instruction(Opcode::Halt, _) | Item::Data(_) => (vec![], false),
+        Item::Instruction(_, _) => (vec![next], false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cfg() {
+        // echoes its inputs until it reads 0
+        let program = vec![3, 12, 1006, 12, 11, 4, 12, 1105, 1, 0, 0, 99, 0];
+        let cfg = Cfg::build(&program);
+        assert_eq!(cfg.blocks.keys().collect::<Vec<_>>(), vec![&0, &5, &11]);
+        assert_eq!(cfg.blocks[&0].successors, vec![11, 5]);
+        assert_eq!(cfg.blocks[&5].successors, vec![0]);
+        assert_eq!(cfg.blocks[&11].successors, vec![]);
+
+        let mut dot = vec![];
+        cfg.write_dot(&mut dot).unwrap();
+        assert_eq!(
+            String::from_utf8(dot).unwrap(),
+            r#"digraph cfg {
+    node [shape=box, fontname="monospace"];
+    b0 [label="0000: in   [12]\l0002: jif  [12], #11\l"];
+    b0 -> b11;
+    b0 -> b5;
+    b5 [label="0005: out  [12]\l0007: jit  #1, #0\l"];
+    b5 -> b0;
+    b11 [label="0011: halt\l"];
+}
+"#
+        );
+
+        // always jumps to the address stored in [3]
+        let cfg = Cfg::build(&[106, 0, 3, 99]);
+        assert_eq!(cfg.blocks.len(), 1);
+        assert_eq!(cfg.blocks[&0].successors, vec![]);
+        assert!(cfg.blocks[&0].indirect);
+    }
+}
@@ -0,0 +1,129 @@
+use super::IO;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, ErrorKind, Write};
+
+const PROMPT: &str = "> ";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mode {
+    /// One number per line of input, one line of output per value.
+    Numbers,
+    /// Lines of text in and out, values outside the ASCII range are printed
+    /// as numbers on their own line.
+    Ascii,
+}
+
+/// IO connecting a program to a user, reading input lines from `input` and
+/// writing output to `output`.
+pub struct ConsoleIO<R, W>
+where
+    R: BufRead,
+    W: Write,
+{
+    input: R,
+    output: W,
+    mode: Mode,
+    pending: VecDeque<i64>,
+}
+
+impl<R, W> ConsoleIO<R, W>
+where
+    R: BufRead,
+    W: Write,
+{
+    pub fn new(input: R, output: W, mode: Mode) -> Self {
+        Self {
+            input,
+            output,
+            mode,
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Err(io::Error::from(ErrorKind::UnexpectedEof));
+        }
+        Ok(line.trim_end_matches(&['\r', '\n'][..]).to_string())
+    }
+
+    fn read_number(&mut self) -> io::Result<i64> {
+        loop {
+            write!(self.output, "{}", PROMPT)?;
+            self.output.flush()?;
+            let line = self.read_line()?;
+            match line.trim().parse() {
+                Ok(val) => return Ok(val),
+                Err(e) => writeln!(self.output, "invalid number: {}", e)?,
+            }
+        }
+    }
+}
+
+impl<R, W> IO for ConsoleIO<R, W>
+where
+    R: BufRead,
+    W: Write,
+{
+    fn get(&mut self) -> io::Result<i64> {
+        match self.mode {
+            Mode::Numbers => self.read_number(),
+            Mode::Ascii => {
+                if self.pending.is_empty() {
+                    let line = self.read_line()?;
+                    self.pending.extend(line.bytes().map(i64::from));
+                    self.pending.push_back(i64::from(b'\n'));
+                }
+                Ok(self.pending.pop_front().unwrap())
+            }
+        }
+    }
+
+    fn put(&mut self, val: i64) -> io::Result<()> {
+        match self.mode {
+            Mode::Ascii if (0..128).contains(&val) => {
+                self.output.write_all(&[val as u8])?;
+            }
+            _ => writeln!(self.output, "{}", val)?,
+        }
+        self.output.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{FaultKind, IntCodeComputer};
+
+    fn session(program: &[i64], mode: Mode, input: &str) -> (FaultKind, String) {
+        let mut output = vec![];
+        let io = ConsoleIO::new(input.as_bytes(), &mut output, mode);
+        let mut computer = IntCodeComputer::new(program.to_vec(), io);
+        let error = computer.run().unwrap_err();
+        drop(computer);
+        (error.kind, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_console_io() {
+        // echoes its input, and outputs 1000 after each newline
+        let program = vec![
+            3, 100, 4, 100, 1008, 100, 10, 101, 1006, 101, 0, 104, 1000, 1105, 1, 0,
+        ];
+        assert_eq!(
+            session(&program, Mode::Numbers, "12\nx\n-3\n"),
+            (
+                FaultKind::InputExhausted,
+                "> 12\n> invalid number: invalid digit found in string\n> -3\n> ".to_string()
+            )
+        );
+        assert_eq!(
+            session(&program, Mode::Ascii, "hi\nyou\n"),
+            (
+                FaultKind::InputExhausted,
+                "hi\n1000\nyou\n1000\n".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,445 @@
+use super::watch::{Access, Action, Hit};
+use super::{disasm::disassemble_at, FaultKind, IntCodeComputer, Snapshot, Status, IO};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::io::{self, BufRead, ErrorKind, Write};
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+const PROMPT: &str = "(debug) ";
+
+/// Number of instructions which can be stepped back through.
+const HISTORY: usize = 100_000;
+
+const HELP: &str = "\
+step [n]            execute n instructions (default 1)
+back [n]            undo the last n instructions (default 1)
+rewind <addr>       undo instructions up to the last one writing to a memory cell
+continue            run until a breakpoint, a watchpoint, an input request or halt
+break <addr>        set a breakpoint
+delete <addr>       remove a breakpoint
+watch <addr>        stop when the value of a memory cell changes
+rwatch <addr>       stop when an instruction reads a memory cell
+trace <addr>        print the reads and writes of a memory cell, without stopping
+unwatch <addr>      remove the watchpoints and traces on a memory cell
+regs                print ip and relative base
+mem <addr> [n]      dump n memory cells (default 8)
+poke <addr> <v>...  write values to memory
+input <v>...        queue input values for the program
+save                save the state of the machine
+load                go back to the state saved with save
+list [addr] [n]     disassemble n instructions (default 10)
+quit                leave the debugger";
+
+/// Most memory cells or instructions printed by a single command.
+const MAX_COUNT: usize = 10_000;
+
+/// Interactive debugger driving an `IntCodeComputer` one step at a time.
+pub struct Debugger<T>
+where
+    T: IO,
+{
+    pub computer: IntCodeComputer<T>,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeMap<usize, i64>,
+    /// Accesses to traced cells not printed yet.
+    traced: Arc<Mutex<Vec<Hit>>>,
+    saved: Option<Snapshot>,
+}
+
+impl<T> Debugger<T>
+where
+    T: IO,
+{
+    pub fn new(mut computer: IntCodeComputer<T>) -> Self {
+        computer.set_history(Some(HISTORY));
+        Self {
+            computer,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            traced: Arc::new(Mutex::new(vec![])),
+            saved: None,
+        }
+    }
+
+    /// Read commands from `input` until it is exhausted or `quit` is entered.
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        self.print_current(&mut output)?;
+        write!(output, "{}", PROMPT)?;
+        output.flush()?;
+
+        for line in input.lines() {
+            let line = line?;
+            let mut words = line.split_whitespace();
+            let command = words.next().unwrap_or("");
+            let args = match words.map(str::parse).collect::<Result<Vec<i64>, _>>() {
+                Ok(args) => args,
+                Err(e) => {
+                    writeln!(output, "invalid argument: {}", e)?;
+                    write!(output, "{}", PROMPT)?;
+                    output.flush()?;
+                    continue;
+                }
+            };
+
+            match self.command(command, &args, &mut output) {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(e) if e.kind() == ErrorKind::InvalidInput => writeln!(output, "{}", e)?,
+                Err(e) => return Err(e),
+            }
+
+            write!(output, "{}", PROMPT)?;
+            output.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Execute a command, and return whether the debugger should be left.
+    /// Invalid arguments are reported as `ErrorKind::InvalidInput` errors.
+    fn command<W: Write>(
+        &mut self,
+        command: &str,
+        args: &[i64],
+        output: &mut W,
+    ) -> io::Result<bool> {
+        match (command, args) {
+            ("", _) => {}
+            ("s", args) | ("step", args) if args.len() <= 1 => {
+                let count = args.first().map_or(Ok(1), |&n| count(n, usize::MAX))?;
+                for _ in 0..count {
+                    if self.step(output)? {
+                        break;
+                    }
+                }
+                self.print_current(output)?;
+            }
+            ("back", args) if args.len() <= 1 => {
+                let count = args.first().map_or(Ok(1), |&n| count(n, usize::MAX))?;
+                match self.computer.step_back(count) {
+                    Ok(true) => {}
+                    Ok(false) => writeln!(output, "no more history")?,
+                    Err(e) => writeln!(output, "error: {}", e)?,
+                }
+                self.refresh_watchpoints();
+                self.print_current(output)?;
+            }
+            ("rewind", &[addr]) => {
+                match self.computer.rewind_to_write(address(addr)?) {
+                    Ok(Some(_)) => {}
+                    Ok(None) => writeln!(output, "no write to {} in history", addr)?,
+                    Err(e) => writeln!(output, "error: {}", e)?,
+                }
+                self.refresh_watchpoints();
+                self.print_current(output)?;
+            }
+            ("c", []) | ("continue", []) => {
+                while !self.step(output)? {}
+                self.print_current(output)?;
+            }
+            ("b", &[addr]) | ("break", &[addr]) => {
+                self.breakpoints.insert(address(addr)?);
+            }
+            ("d", &[addr]) | ("delete", &[addr]) => {
+                self.breakpoints.remove(&address(addr)?);
+            }
+            ("w", &[addr]) | ("watch", &[addr]) => {
+                let addr = address(addr)?;
+                let value = self.computer.read_memory(addr);
+                self.watchpoints.insert(addr, value);
+            }
+            ("rwatch", &[addr]) => {
+                self.computer
+                    .watch(address(addr)?, Access::Read, Action::Pause);
+            }
+            ("trace", &[addr]) => {
+                let addr = address(addr)?;
+                for access in [Access::Read, Access::Write] {
+                    let traced = self.traced.clone();
+                    let log = move |hit: &Hit| traced.lock().unwrap().push(hit.clone());
+                    self.computer
+                        .watch(addr, access, Action::Call(Arc::new(log)));
+                }
+            }
+            ("unwatch", &[addr]) => {
+                let addr = address(addr)?;
+                self.watchpoints.remove(&addr);
+                self.computer.unwatch(addr, Access::Read);
+                self.computer.unwatch(addr, Access::Write);
+            }
+            ("r", []) | ("regs", []) => writeln!(
+                output,
+                "ip={} rb={}",
+                self.computer.ip, self.computer.relative_base
+            )?,
+            ("x", args) | ("mem", args) if !args.is_empty() && args.len() <= 2 => {
+                let start = address(args[0])?;
+                let count = args.get(1).map_or(Ok(8), |&n| count(n, MAX_COUNT))?;
+                let end = start
+                    .checked_add(count)
+                    .ok_or_else(|| invalid("range out of memory"))?;
+                let values = (start..end)
+                    .map(|addr| self.computer.read_memory(addr).to_string())
+                    .collect::<Vec<_>>();
+                writeln!(output, "{:04}: {}", start, values.join(" "))?;
+            }
+            ("poke", [addr, values @ ..]) if !values.is_empty() => {
+                // written behind the program's back: no watchpoint, trace or
+                // history sees these writes
+                let start = address(*addr)?;
+                for (i, &value) in values.iter().enumerate() {
+                    let result = start
+                        .checked_add(i)
+                        .ok_or(FaultKind::WordOutOfRange)
+                        .and_then(|addr| self.computer.memory.set(addr, value));
+                    if let Err(e) = result {
+                        writeln!(output, "error: {}", e)?;
+                        break;
+                    }
+                }
+                self.refresh_watchpoints();
+            }
+            ("i", values) | ("input", values) => {
+                for &value in values {
+                    self.computer.push_input(value);
+                }
+            }
+            ("save", []) => self.saved = Some(self.computer.snapshot()),
+            ("load", []) => match &self.saved {
+                Some(snapshot) => {
+                    self.computer.restore(snapshot);
+                    self.refresh_watchpoints();
+                    self.print_current(output)?;
+                }
+                None => writeln!(output, "no saved state")?,
+            },
+            ("l", args) | ("list", args) if args.len() <= 2 => {
+                let mut addr = args.first().map_or(Ok(self.computer.ip), |&a| address(a))?;
+                let count = args.get(1).map_or(Ok(10), |&n| count(n, MAX_COUNT))?;
+                for _ in 0..count {
+                    let line = disassemble_at(self.computer.memory.as_slice(), addr);
+                    writeln!(output, "{}", line)?;
+                    addr = match addr.checked_add(line.item.size()) {
+                        Some(addr) => addr,
+                        None => break,
+                    };
+                }
+            }
+            ("h", []) | ("help", []) => writeln!(output, "{}", HELP)?,
+            ("q", []) | ("quit", []) => return Ok(true),
+            _ => writeln!(output, "invalid command, type help for a list of commands")?,
+        }
+        Ok(false)
+    }
+
+    /// Execute a single instruction and return whether execution should stop.
+    fn step<W: Write>(&mut self, output: &mut W) -> io::Result<bool> {
+        let mut stop = false;
+
+        let status = self.computer.step();
+        let traced = mem::take(&mut *self.traced.lock().unwrap());
+        for hit in traced {
+            writeln!(output, "trace: {}", hit)?;
+        }
+        match status {
+            Ok(Status::Running) | Ok(Status::BudgetExhausted) => {}
+            Ok(Status::Output(value)) => writeln!(output, "output: {}", value)?,
+            Ok(Status::WaitingForInput) => {
+                writeln!(output, "waiting for input")?;
+                stop = true;
+            }
+            Ok(Status::Halted) => {
+                writeln!(output, "halted")?;
+                stop = true;
+            }
+            Ok(Status::Watchpoint(hit)) => {
+                writeln!(output, "watchpoint: {}", hit)?;
+                stop = true;
+            }
+            Err(e) => {
+                writeln!(output, "error: {}", e)?;
+                stop = true;
+            }
+        }
+
+        for (&addr, old) in self.watchpoints.iter_mut() {
+            let new = self.computer.read_memory(addr);
+            if new != *old {
+                writeln!(output, "watchpoint {}: {} -> {}", addr, old, new)?;
+                *old = new;
+                stop = true;
+            }
+        }
+
+        if self.breakpoints.contains(&self.computer.ip) {
+            writeln!(output, "breakpoint {}", self.computer.ip)?;
+            stop = true;
+        }
+
+        Ok(stop)
+    }
+
+    /// Take the current values of watched cells, after going back in time.
+    fn refresh_watchpoints(&mut self) {
+        for (&addr, value) in self.watchpoints.iter_mut() {
+            *value = self.computer.read_memory(addr);
+        }
+    }
+
+    fn print_current<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let line = disassemble_at(self.computer.memory.as_slice(), self.computer.ip);
+        writeln!(output, "=> {}", line)
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidInput, message)
+}
+
+fn address(arg: i64) -> io::Result<usize> {
+    usize::try_from(arg).map_err(|_| invalid(&format!("invalid address {}", arg)))
+}
+
+fn count(arg: i64, max: usize) -> io::Result<usize> {
+    match usize::try_from(arg) {
+        Ok(count) if count <= max => Ok(count),
+        _ => Err(invalid(&format!("invalid count {}", arg))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::NoIO;
+
+    fn session(program: Vec<i64>, commands: &str) -> String {
+        let mut debugger = Debugger::new(IntCodeComputer::new(program, NoIO {}));
+        let mut output = vec![];
+        debugger.run(commands.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap().replace(PROMPT, "")
+    }
+
+    #[test]
+    fn test_debugger() {
+        let program = vec![3, 9, 1002, 9, 2, 9, 4, 9, 99, 0];
+        assert_eq!(
+            session(
+                program,
+                "break 6\ncontinue\ninput 21\nwatch 9\nc\nc\nregs\nmem 8 2\ns\nc\nq\n"
+            ),
+            "=> 0000: in   [9]
+waiting for input
+=> 0000: in   [9]
+watchpoint 9: 0 -> 21
+=> 0002: mul  [9], #2, [9]
+watchpoint 9: 21 -> 42
+breakpoint 6
+=> 0006: out  [9]
+ip=6 rb=0
+0008: 99 42
+output: 42
+=> 0008: halt
+halted
+=> 0008: halt
+"
+        );
+
+        let program = vec![3, 9, 1002, 9, 2, 9, 4, 9, 99, 0];
+        assert_eq!(
+            session(
+                program,
+                "input 21\nc\nback 2\nmem 9 1\nrewind 9\nrewind 9\nrewind 9\nback\nc\nq\n"
+            ),
+            "=> 0000: in   [9]
+output: 42
+halted
+=> 0008: halt
+=> 0006: out  [9]
+0009: 42
+=> 0002: mul  [9], #2, [9]
+=> 0000: in   [9]
+no write to 9 in history
+=> 0000: in   [9]
+no more history
+=> 0000: in   [9]
+output: 42
+halted
+=> 0008: halt
+"
+        );
+
+        // addresses and counts out of range are rejected
+        let program = vec![104, 7, 99];
+        assert_eq!(
+            session(
+                program,
+                "mem -1\nmem 0 -1\nlist -1\nbreak -5\nstep -1\nmem 9223372036854775807 2\nlist 2 1\nq\n"
+            ),
+            "=> 0000: out  #7
+invalid address -1
+invalid count -1
+invalid address -1
+invalid address -5
+invalid count -1
+9223372036854775807: 0 0
+0002: halt
+"
+        );
+
+        let program = vec![1001, 7, 1, 7, 4, 7, 99, 41];
+        assert_eq!(
+            session(program, "watch 7\npoke 7 1 2\nmem 7 2\nc\nq\n"),
+            "=> 0000: add  [7], #1, [7]
+0007: 1 2
+watchpoint 7: 1 -> 2
+=> 0004: out  [7]
+"
+        );
+
+        let program = vec![1001, 7, 1, 7, 4, 7, 99, 41];
+        assert_eq!(
+            session(program, "rwatch 7\nc\nc\nq\n"),
+            "=> 0000: add  [7], #1, [7]
+watchpoint: read of 41 at [7] by instruction 0
+=> 0004: out  [7]
+output: 42
+watchpoint: read of 42 at [7] by instruction 4
+=> 0006: halt
+"
+        );
+
+        let program = vec![1001, 7, 1, 7, 4, 7, 99, 41];
+        assert_eq!(
+            session(program, "trace 7\npoke 7 1\nc\nunwatch 7\nback 3\nc\nq\n"),
+            "=> 0000: add  [7], #1, [7]
+trace: read of 1 at [7] by instruction 0
+trace: write of 2 at [7] by instruction 0
+trace: read of 2 at [7] by instruction 4
+output: 2
+halted
+=> 0006: halt
+=> 0000: add  [7], #1, [7]
+output: 2
+halted
+=> 0006: halt
+"
+        );
+
+        let program = vec![1001, 7, 1, 7, 4, 7, 99, 41];
+        assert_eq!(
+            session(program, "load\nsave\nc\nload\nmem 7 1\nc\nq\n"),
+            "=> 0000: add  [7], #1, [7]
+no saved state
+output: 42
+halted
+=> 0006: halt
+=> 0000: add  [7], #1, [7]
+0007: 41
+output: 42
+halted
+=> 0006: halt
+"
+        );
+    }
+}
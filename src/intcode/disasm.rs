@@ -0,0 +1,190 @@
+use super::{decode_instruction, encode_instruction, Opcode, ParameterMode};
+use std::fmt::{self, Display, Formatter};
+
+/// Maximum number of consecutive data words listed on a single line.
+const DATA_WORDS_PER_LINE: usize = 8;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Operand {
+    Position(i64),
+    Immediate(i64),
+    Relative(i64),
+}
+
+impl Operand {
+    fn new(mode: ParameterMode, value: i64) -> Self {
+        match mode {
+            ParameterMode::Position => Operand::Position(value),
+            ParameterMode::Immediate => Operand::Immediate(value),
+            ParameterMode::Relative => Operand::Relative(value),
+        }
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Position(n) => write!(f, "[{}]", n),
+            Operand::Immediate(n) => write!(f, "#{}", n),
+            Operand::Relative(n) if *n < 0 => write!(f, "[rb-{}]", n.unsigned_abs()),
+            Operand::Relative(n) => write!(f, "[rb+{}]", n),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Item {
+    Instruction(Opcode, Vec<Operand>),
+    Data(Vec<i64>),
+}
+
+impl Item {
+    /// Number of memory words covered by this item.
+    pub fn size(&self) -> usize {
+        match self {
+            Item::Instruction(_, operands) => operands.len() + 1,
+            Item::Data(words) => words.len(),
+        }
+    }
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (mnemonic, operands) = match self {
+            Item::Instruction(opcode, operands) => (
+                opcode.to_string(),
+                operands.iter().map(Operand::to_string).collect::<Vec<_>>(),
+            ),
+            Item::Data(words) => (
+                String::from("data"),
+                words.iter().map(i64::to_string).collect(),
+            ),
+        };
+        if operands.is_empty() {
+            write!(f, "{}", mnemonic)
+        } else {
+            write!(f, "{:<4} {}", mnemonic, operands.join(", "))
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Line {
+    pub address: usize,
+    pub item: Item,
+}
+
+impl Display for Line {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}: {}", self.address, self.item)
+    }
+}
+
+/// Disassemble a program with a linear sweep from address 0.
+///
+/// Words which cannot be the start of a valid instruction are listed as data,
+/// so that the sweep can resynchronize on the next instruction. Since a
+/// program may patch its own code, the listing only reflects the memory as
+/// given.
+pub fn disassemble(program: &[i64]) -> Vec<Line> {
+    let mut lines: Vec<Line> = vec![];
+    let mut address = 0;
+
+    while address < program.len() {
+        let item = decode(&program[address..]);
+
+        match (lines.last_mut(), item) {
+            (
+                Some(Line {
+                    item: Item::Data(words),
+                    ..
+                }),
+                None,
+            ) if words.len() < DATA_WORDS_PER_LINE => {
+                words.push(program[address]);
+            }
+            (_, item) => lines.push(Line {
+                address,
+                item: item.unwrap_or_else(|| Item::Data(vec![program[address]])),
+            }),
+        }
+
+        address = lines.last().map_or(0, |l| l.address + l.item.size());
+    }
+
+    lines
+}
+
+/// Disassemble the single instruction at the given address.
+pub fn disassemble_at(program: &[i64], address: usize) -> Line {
+    let item = program
+        .get(address..)
+        .and_then(decode)
+        .unwrap_or_else(|| Item::Data(vec![program.get(address).cloned().unwrap_or(0)]));
+    Line { address, item }
+}
+
+/// Decode the instruction at the start of `words`, if it is a valid one which
+/// the assembler would encode the same way.
+fn decode(words: &[i64]) -> Option<Item> {
+    let (opcode, modes) = decode_instruction(*words.first()?).ok()?;
+    let modes = &modes[..opcode.arity()];
+    let params = words.get(1..=opcode.arity())?;
+
+    if encode_instruction(opcode, modes) != words[0] {
+        return None;
+    }
+    if opcode.has_dest() && modes.last() == Some(&ParameterMode::Immediate) {
+        return None;
+    }
+
+    let operands = modes
+        .iter()
+        .zip(params)
+        .map(|(&mode, &value)| Operand::new(mode, value))
+        .collect();
+    Some(Item::Instruction(opcode, operands))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(program: &[i64]) -> Vec<String> {
+        disassemble(program).iter().map(Line::to_string).collect()
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let program = [
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        assert_eq!(
+            listing(&program),
+            vec![
+                "0000: arb  #1",
+                "0002: out  [rb-1]",
+                "0004: add  [100], #1, [100]",
+                "0008: eq   [100], #16, [101]",
+                "0012: jif  [101], #0",
+                "0015: halt",
+            ]
+        );
+        assert_eq!(
+            listing(&[204, i64::MIN, 99]),
+            vec!["0000: out  [rb-9223372036854775808]", "0002: halt"]
+        );
+    }
+
+    #[test]
+    fn test_disassemble_data() {
+        assert_eq!(
+            listing(&[1102, 3, 4, 9, 99, -1, 42, 11101, 1, 0]),
+            vec![
+                "0000: mul  #3, #4, [9]",
+                "0004: halt",
+                "0005: data -1, 42, 11101, 1, 0",
+            ]
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use super::FaultKind;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+type Handler<W> = dyn Fn(&[W]) -> Result<Option<W>, FaultKind> + Send + Sync;
+
+/// Instruction added to the Intcode instruction set, registered on a machine
+/// with `IntCodeComputer::register`.
+///
+/// Its parameters follow the instruction and use the usual parameter modes.
+/// When it executes, the host function is called with their values, followed
+/// by the address of the destination if it has one, and the value it returns
+/// is written there. Extensions cannot jump: execution always continues with
+/// the next instruction.
+#[derive(Clone)]
+pub struct Extension<W = i64> {
+    pub mnemonic: String,
+    pub arity: usize,
+    pub has_dest: bool,
+    handler: Arc<Handler<W>>,
+}
+
+impl<W> Extension<W> {
+    /// Create an instruction taking `arity` parameters, the last of which is
+    /// a destination if `has_dest` is set.
+    ///
+    /// Panics if it has more parameters than an instruction has modes for.
+    pub fn new<F>(mnemonic: &str, arity: usize, has_dest: bool, handler: F) -> Self
+    where
+        F: Fn(&[W]) -> Result<Option<W>, FaultKind> + Send + Sync + 'static,
+    {
+        assert!(arity <= 3, "an instruction has at most 3 parameters");
+        assert!(arity > 0 || !has_dest, "a destination is a parameter");
+        Self {
+            mnemonic: String::from(mnemonic),
+            arity,
+            has_dest,
+            handler: Arc::new(handler),
+        }
+    }
+
+    pub fn call(&self, operands: &[W]) -> Result<Option<W>, FaultKind> {
+        (self.handler)(operands)
+    }
+}
+
+impl<W> Debug for Extension<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extension")
+            .field("mnemonic", &self.mnemonic)
+            .field("arity", &self.arity)
+            .field("has_dest", &self.has_dest)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{IntCodeComputer, IntCodeError, QueueIO, Status};
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_extensions() {
+        let printed = Arc::new(Mutex::new(vec![]));
+        let log = printed.clone();
+
+        let mut computer = IntCodeComputer::new(
+            // nop, print [10], [10] <- max(#5, [10]), print [10], halt
+            vec![50, 51, 10, 152, 5, 10, 10, 51, 10, 99, 3],
+            QueueIO::new(&[]),
+        );
+        computer.register(50, Extension::new("nop", 0, false, |_: &[i64]| Ok(None)));
+        computer.register(
+            51,
+            Extension::new("print", 1, false, move |o: &[i64]| {
+                log.lock().unwrap().push(o[0]);
+                Ok(None)
+            }),
+        );
+        computer.register(
+            52,
+            Extension::new("max", 3, true, |o: &[i64]| Ok(Some(o[0].max(o[1])))),
+        );
+        assert_eq!(computer.run(), Ok(Status::Halted));
+        assert_eq!(*printed.lock().unwrap(), vec![3, 5]);
+        assert_eq!(computer.memory.get(10), 5);
+
+        // host functions may fault, and unknown opcodes still do
+        let mut computer = IntCodeComputer::new(vec![1153, 1, 0, 4], QueueIO::new(&[]));
+        computer.register(
+            53,
+            Extension::new("div", 3, true, |o: &[i64]| {
+                o[0].checked_div(o[1])
+                    .map(Some)
+                    .ok_or(FaultKind::ArithmeticOverflow)
+            }),
+        );
+        assert_eq!(
+            computer.step(),
+            Err(IntCodeError {
+                ip: 0,
+                instruction: 1153,
+                kind: FaultKind::ArithmeticOverflow,
+            })
+        );
+        assert_eq!(
+            IntCodeComputer::new(vec![53], QueueIO::new(&[])).step(),
+            Err(IntCodeError {
+                ip: 0,
+                instruction: 53,
+                kind: FaultKind::InvalidOpcode(53),
+            })
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use super::{
+    encode_instruction, Budget, FaultKind, IntCodeComputer, Opcode, ParameterMode, QueueIO, Status,
+};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Instructions a generated program may execute.
+const BUDGET: u64 = 10_000;
+
+/// Highest address a generated program may write to.
+const MEMORY_LIMIT: usize = 4096;
+
+/// Instructions in a generated program.
+const PROGRAM_SIZE: usize = 24;
+
+/// Inputs available to a generated program.
+const INPUTS: usize = 8;
+
+const OPCODES: [Opcode; 10] = [
+    Opcode::Add,
+    Opcode::Mul,
+    Opcode::In,
+    Opcode::Out,
+    Opcode::Jit,
+    Opcode::Jif,
+    Opcode::Lt,
+    Opcode::Eq,
+    Opcode::Arb,
+    Opcode::Halt,
+];
+
+const MODES: [ParameterMode; 3] = [
+    ParameterMode::Position,
+    ParameterMode::Immediate,
+    ParameterMode::Relative,
+];
+
+/// Xorshift generator, so that a seed always leads to the same programs.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift is stuck on 0
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Random number in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// Generate a program made of valid instructions. Its parameters are mostly
+/// addresses within the program, so that it reads and patches its own code.
+pub fn generate(rng: &mut Rng) -> Vec<i64> {
+    let mut program = vec![];
+    for _ in 0..PROGRAM_SIZE {
+        let opcode = OPCODES[rng.below(OPCODES.len())];
+        let modes = (0..opcode.arity())
+            .map(|idx| match opcode.has_dest() && idx == opcode.arity() - 1 {
+                true => MODES[[0, 2][rng.below(2)]],
+                false => MODES[rng.below(MODES.len())],
+            })
+            .collect::<Vec<_>>();
+
+        program.push(encode_instruction(opcode, &modes));
+        for _ in 0..opcode.arity() {
+            let value = match rng.below(8) {
+                0 => rng.next_u64() as i64,
+                1 | 2 => rng.below(21) as i64 - 10,
+                _ => rng.below(PROGRAM_SIZE * 3) as i64,
+            };
+            program.push(value);
+        }
+    }
+    program.push(i64::from(Opcode::Halt));
+    program
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Stop {
+    Halted,
+    BudgetExhausted,
+    Fault { ip: usize, kind: FaultKind },
+}
+
+/// Observable result of running a program.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Outcome {
+    pub stop: Stop,
+    pub outputs: Vec<i64>,
+    pub memory: Vec<i64>,
+}
+
+/// Implementation of the machine under test, running a program with inputs.
+pub type Vm = fn(&[i64], &[i64]) -> Outcome;
+
+/// Run a program on `IntCodeComputer`.
+pub fn computer(program: &[i64], inputs: &[i64]) -> Outcome {
+    let mut computer = IntCodeComputer::new(program.to_vec(), QueueIO::new(inputs));
+    computer.memory.set_limit(Some(MEMORY_LIMIT));
+    let stop = match computer.run_with_budget(Budget::instructions(BUDGET)) {
+        Ok(Status::BudgetExhausted) => Stop::BudgetExhausted,
+        Ok(_) => Stop::Halted,
+        Err(e) => Stop::Fault {
+            ip: e.ip,
+            kind: e.kind,
+        },
+    };
+
+    Outcome {
+        stop,
+        outputs: computer.io.drain_outputs(),
+        memory: computer.memory.as_slice().to_vec(),
+    }
+}
+
+/// Run a program on a straightforward interpreter, written independently from
+/// `IntCodeComputer` to check it against.
+pub fn reference(program: &[i64], inputs: &[i64]) -> Outcome {
+    let mut vm = Reference {
+        memory: program.to_vec(),
+        inputs: inputs.to_vec(),
+        outputs: vec![],
+        ip: 0,
+        relative_base: 0,
+    };
+    let mut executed = 0;
+    let stop = loop {
+        if executed >= BUDGET {
+            break Stop::BudgetExhausted;
+        }
+        match vm.step() {
+            Ok(true) => executed += 1,
+            Ok(false) => break Stop::Halted,
+            Err(kind) => break Stop::Fault { ip: vm.ip, kind },
+        }
+    };
+
+    Outcome {
+        stop,
+        outputs: vm.outputs,
+        memory: vm.memory,
+    }
+}
+
+struct Reference {
+    memory: Vec<i64>,
+    inputs: Vec<i64>,
+    outputs: Vec<i64>,
+    ip: usize,
+    relative_base: i64,
+}
+
+impl Reference {
+    /// Execute an instruction, and return whether the program goes on.
+    fn step(&mut self) -> Result<bool, FaultKind> {
+        let instruction = self.read(self.ip);
+        let opcode = match instruction % 100 {
+            1 => Opcode::Add,
+            2 => Opcode::Mul,
+            3 => Opcode::In,
+            4 => Opcode::Out,
+            5 => Opcode::Jit,
+            6 => Opcode::Jif,
+            7 => Opcode::Lt,
+            8 => Opcode::Eq,
+            9 => Opcode::Arb,
+            99 => Opcode::Halt,
+            n => return Err(FaultKind::InvalidOpcode(n)),
+        };
+        let mut modes = [0; 3];
+        for (idx, mode) in modes.iter_mut().enumerate() {
+            *mode = (instruction / 10i64.pow(idx as u32 + 2)) % 10;
+            if !(0..=2).contains(mode) {
+                return Err(FaultKind::InvalidParameterMode(*mode));
+            }
+        }
+
+        // parameters are addresses, apart from immediate ones
+        let arity = opcode.arity();
+        let mut params = [0; 3];
+        for idx in 0..arity {
+            let word = self.read(self.ip + idx + 1);
+            let is_dest = idx == arity - 1 && opcode.has_dest();
+            params[idx] = match modes[idx] {
+                0 => address(word)?,
+                1 if is_dest => return Err(FaultKind::ImmediateDestination),
+                1 => self.ip + idx + 1,
+                _ => address(
+                    word.checked_add(self.relative_base)
+                        .ok_or(FaultKind::WordOutOfRange)?,
+                )?,
+            };
+        }
+        let value = |idx: usize| self.read(params[idx]);
+
+        match opcode {
+            Opcode::Add => self.write(params[2], value(0).wrapping_add(value(1)))?,
+            Opcode::Mul => self.write(params[2], value(0).wrapping_mul(value(1)))?,
+            Opcode::Lt => self.write(params[2], (value(0) < value(1)) as i64)?,
+            Opcode::Eq => self.write(params[2], (value(0) == value(1)) as i64)?,
+            Opcode::In => {
+                if self.inputs.is_empty() {
+                    return Err(FaultKind::InputExhausted);
+                }
+                let input = self.inputs.remove(0);
+                self.write(params[0], input)?;
+            }
+            Opcode::Out => self.outputs.push(value(0)),
+            Opcode::Jit | Opcode::Jif => {
+                if (value(0) != 0) == (opcode == Opcode::Jit) {
+                    self.ip = address(value(1))?;
+                    return Ok(true);
+                }
+            }
+            Opcode::Arb => {
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(value(0))
+                    .ok_or(FaultKind::WordOutOfRange)?;
+            }
+            Opcode::Halt => return Ok(false),
+        }
+        self.ip += arity + 1;
+        Ok(true)
+    }
+
+    fn read(&self, address: usize) -> i64 {
+        self.memory.get(address).cloned().unwrap_or(0)
+    }
+
+    fn write(&mut self, address: usize, value: i64) -> Result<(), FaultKind> {
+        if address > MEMORY_LIMIT {
+            return Err(FaultKind::AddressOutOfRange(address));
+        }
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1, 0);
+        }
+        self.memory[address] = value;
+        Ok(())
+    }
+}
+
+fn address(word: i64) -> Result<usize, FaultKind> {
+    if word < 0 {
+        Err(FaultKind::NegativeAddress(word))
+    } else {
+        Ok(word as usize)
+    }
+}
+
+#[derive(Debug)]
+pub enum FailureKind {
+    Panic,
+    /// The machine and the reference disagree.
+    Mismatch {
+        expected: Box<Outcome>,
+        found: Box<Outcome>,
+    },
+}
+
+/// Program which broke the machine, with the seed it was generated from.
+#[derive(Debug)]
+pub struct Failure {
+    pub seed: u64,
+    pub program: Vec<i64>,
+    pub inputs: Vec<i64>,
+    pub kind: FailureKind,
+}
+
+/// Run `iterations` generated programs on `vm`, each from its own seed
+/// counting up from `seed` (wrapping around), and check that it does not
+/// panic and, if given, that it behaves like `reference`.
+pub fn fuzz(seed: u64, iterations: u64, vm: Vm, reference: Option<Vm>) -> Result<(), Failure> {
+    for seed in (0..iterations).map(|i| seed.wrapping_add(i)) {
+        let mut rng = Rng::new(seed);
+        let program = generate(&mut rng);
+        let inputs = (0..INPUTS)
+            .map(|_| rng.below(21) as i64 - 10)
+            .collect::<Vec<_>>();
+        let failure = |kind| Failure {
+            seed,
+            program: program.clone(),
+            inputs: inputs.clone(),
+            kind,
+        };
+
+        let found = catch_unwind(AssertUnwindSafe(|| vm(&program, &inputs)))
+            .map_err(|_| failure(FailureKind::Panic))?;
+        if let Some(reference) = reference {
+            let expected = reference(&program, &inputs);
+            if expected != found {
+                return Err(failure(FailureKind::Mismatch {
+                    expected: Box::new(expected),
+                    found: Box::new(found),
+                }));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzz() {
+        assert!(fuzz(0, 500, computer, Some(reference)).is_ok());
+
+        let failure = fuzz(
+            0,
+            500,
+            computer,
+            Some(|_: &[i64], _: &[i64]| Outcome {
+                stop: Stop::Halted,
+                outputs: vec![],
+                memory: vec![],
+            }),
+        )
+        .unwrap_err();
+        assert_eq!(failure.seed, 0);
+        assert!(matches!(failure.kind, FailureKind::Mismatch { .. }));
+    }
+
+    #[test]
+    fn test_fuzz_seeds_wrap() {
+        assert!(fuzz(u64::MAX - 1, 5, computer, Some(reference)).is_ok());
+    }
+}
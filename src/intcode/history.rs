@@ -0,0 +1,147 @@
+use super::Status;
+use std::collections::VecDeque;
+
+/// What an instruction changed, to undo it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry<W = i64> {
+    pub ip: usize,
+    pub relative_base: i64,
+    /// Cells written, with their value before the write.
+    pub writes: Vec<(usize, W)>,
+    /// Input value consumed.
+    pub input: Option<W>,
+}
+
+/// Undo log of the last instructions executed by an `IntCodeComputer`.
+#[derive(Clone, Debug, Default)]
+pub struct History<W = i64> {
+    entries: VecDeque<Entry<W>>,
+    capacity: usize,
+    /// Entry of the instruction being executed.
+    current: Option<Entry<W>>,
+}
+
+impl<W> History<W> {
+    /// Create a log keeping the last `capacity` instructions.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+            current: None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn begin(&mut self, ip: usize, relative_base: i64) {
+        self.current = Some(Entry {
+            ip,
+            relative_base,
+            writes: vec![],
+            input: None,
+        });
+    }
+
+    /// Log the instruction started by `begin`, given the status of its
+    /// step, unless it did not execute because it waited for input or
+    /// faulted.
+    pub fn end<E>(&mut self, status: &Result<Status<W>, E>) {
+        let entry = match self.current.take() {
+            Some(entry) => entry,
+            None => return,
+        };
+        if matches!(status, Ok(Status::WaitingForInput) | Err(_)) {
+            return;
+        }
+
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        if self.capacity > 0 {
+            self.entries.push_back(entry);
+        }
+    }
+
+    pub fn write(&mut self, address: usize, old: W) {
+        if let Some(entry) = &mut self.current {
+            entry.writes.push((address, old));
+        }
+    }
+
+    pub fn input(&mut self, value: W) {
+        if let Some(entry) = &mut self.current {
+            entry.input = Some(value);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<Entry<W>> {
+        self.entries.pop_back()
+    }
+
+    /// Number of instructions to undo to get back before the last write to
+    /// `address`.
+    pub fn since_write(&self, address: usize) -> Option<usize> {
+        self.entries
+            .iter()
+            .rev()
+            .position(|entry| entry.writes.iter().any(|&(a, _)| a == address))
+            .map(|steps| steps + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::{FaultKind, IntCodeComputer, NoIO, Status};
+
+    #[test]
+    fn test_step_back() {
+        // [12] <- in, [12] <- [12] * #2, out [12], arb #5, halt
+        let program = vec![3, 12, 1002, 12, 2, 12, 4, 12, 109, 5, 99, 0, 0];
+        let mut start = IntCodeComputer::new(program, NoIO {});
+        start.push_input(21);
+        let mut computer = start.clone();
+        computer.set_history(Some(100));
+        assert_eq!(computer.resume(), Ok(Status::Output(42)));
+        assert_eq!(computer.resume(), Ok(Status::Halted));
+        let end = computer.snapshot();
+
+        // back before the multiplication, then all the way to the start
+        assert_eq!(computer.rewind_to_write(12), Ok(Some(4)));
+        assert_eq!(computer.ip, 2);
+        assert_eq!(computer.memory.get(12), 21);
+        assert_eq!(computer.step_back(5), Ok(false));
+        assert_eq!(computer.snapshot(), start.snapshot());
+
+        // replaying consumes the same input again
+        assert_eq!(computer.resume(), Ok(Status::Output(42)));
+        assert_eq!(computer.resume(), Ok(Status::Halted));
+        assert_eq!(computer.snapshot(), end);
+        assert_eq!(computer.rewind_to_write(0), Ok(None));
+
+        // only the last instructions are kept
+        let mut computer = start;
+        computer.set_history(Some(2));
+        assert_eq!(computer.run(), Ok(Status::Halted));
+        assert_eq!(computer.step_back(2), Ok(true));
+        assert_eq!(computer.ip, 8);
+        assert_eq!(computer.step_back(1), Ok(false));
+
+        // instructions changing nothing are undone too
+        let mut computer = IntCodeComputer::new(vec![1106, 0, 0], NoIO {});
+        computer.set_history(Some(100));
+        for _ in 0..3 {
+            assert_eq!(computer.step(), Ok(Status::Running));
+        }
+        assert_eq!(computer.step_back(3), Ok(true));
+        assert_eq!(computer.step_back(1), Ok(false));
+
+        // a cell past a lowered memory limit cannot be restored
+        let mut computer = IntCodeComputer::new(vec![1101, 1, 1, 10, 99], NoIO {});
+        computer.set_history(Some(100));
+        assert_eq!(computer.step(), Ok(Status::Running));
+        computer.memory.set_limit(Some(5));
+        assert_eq!(computer.step_back(1), Err(FaultKind::AddressOutOfRange(10)));
+    }
+}
@@ -0,0 +1,90 @@
+use super::word::Word;
+use super::FaultKind;
+use std::collections::HashMap;
+use std::ops::Index;
+
+/// Highest address up to which memory is kept in a contiguous vector,
+/// addresses above it are stored sparsely.
+const DENSE_MAX: usize = 1 << 16;
+
+/// Memory of an Intcode machine, where every cell not written to holds 0.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Memory<W = i64> {
+    dense: Vec<W>,
+    sparse: HashMap<usize, W>,
+    limit: Option<usize>,
+    zero: W,
+}
+
+impl<W: Word> Memory<W> {
+    pub fn new(program: Vec<W>) -> Self {
+        Self {
+            dense: program,
+            sparse: HashMap::new(),
+            limit: None,
+            zero: W::zero(),
+        }
+    }
+
+    /// Make writes above `limit` fail instead of growing the memory.
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+    }
+
+    pub fn get(&self, idx: usize) -> W {
+        self[idx].clone()
+    }
+
+    pub fn set(&mut self, idx: usize, val: W) -> Result<(), FaultKind> {
+        if self.limit.is_some_and(|limit| idx > limit) {
+            return Err(FaultKind::AddressOutOfRange(idx));
+        }
+
+        if idx < self.dense.len() {
+            self.dense[idx] = val;
+        } else if idx <= DENSE_MAX.max(self.dense.len()) {
+            self.dense.resize(idx + 1, W::zero());
+            self.dense[idx] = val;
+        } else {
+            self.sparse.insert(idx, val);
+        }
+        Ok(())
+    }
+
+    /// Contiguous memory starting at address 0, which holds the program.
+    pub fn as_slice(&self) -> &[W] {
+        &self.dense
+    }
+}
+
+impl<W: Word> Index<usize> for Memory<W> {
+    type Output = W;
+
+    fn index(&self, idx: usize) -> &W {
+        match self.dense.get(idx) {
+            Some(val) => val,
+            None => self.sparse.get(&idx).unwrap_or(&self.zero),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory() {
+        let mut memory: Memory = Memory::new(vec![1, 2, 3]);
+        assert_eq!(memory.set(5, 6), Ok(()));
+        assert_eq!(memory.as_slice(), &[1, 2, 3, 0, 0, 6]);
+
+        assert_eq!(memory.set(1_000_000_000_000, 7), Ok(()));
+        assert_eq!(memory.as_slice().len(), 6);
+        assert_eq!(memory[1_000_000_000_000], 7);
+        assert_eq!(memory.get(999_999_999_999), 0);
+
+        memory.set_limit(Some(1000));
+        assert_eq!(memory.set(1000, 1), Ok(()));
+        assert_eq!(memory.set(1001, 1), Err(FaultKind::AddressOutOfRange(1001)));
+    }
+}
@@ -0,0 +1,167 @@
+use super::scheduler::Scheduler;
+use super::{Budget, IntCodeComputer, IntCodeError, NoIO};
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum NetworkErrorKind {
+    Fault(IntCodeError),
+    BudgetExhausted,
+    /// All machines still running wait for an input, listed by id.
+    Deadlock(Vec<usize>),
+    /// The machine halted while the others expected it to keep running.
+    Halted,
+    /// The machine sent a packet to an address nobody has.
+    InvalidAddress(i64),
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct NetworkError {
+    pub machine: usize,
+    pub kind: NetworkErrorKind,
+}
+
+impl Display for NetworkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            NetworkErrorKind::Fault(e) => write!(f, "machine {}: {}", self.machine, e),
+            NetworkErrorKind::BudgetExhausted => {
+                write!(
+                    f,
+                    "machine {}: did not halt within its budget",
+                    self.machine
+                )
+            }
+            NetworkErrorKind::Deadlock(waiting) => write!(
+                f,
+                "machine {}: deadlock, machines {:?} all wait for input",
+                self.machine, waiting
+            ),
+            NetworkErrorKind::Halted => write!(f, "machine {}: halted", self.machine),
+            NetworkErrorKind::InvalidAddress(address) => write!(
+                f,
+                "machine {}: sent a packet to invalid address {}",
+                self.machine, address
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+/// Machines whose outputs are wired to the inputs of other machines.
+#[derive(Clone, Default)]
+pub struct Network {
+    programs: Vec<Vec<i64>>,
+    links: Vec<Vec<usize>>,
+    seeds: Vec<Vec<i64>>,
+    taps: Vec<bool>,
+    budget: Option<Budget>,
+}
+
+impl Network {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a machine running `program`, and return its id.
+    pub fn add_machine(&mut self, program: Vec<i64>) -> usize {
+        self.programs.push(program);
+        self.links.push(vec![]);
+        self.seeds.push(vec![]);
+        self.taps.push(false);
+        self.programs.len() - 1
+    }
+
+    /// Send the outputs of machine `from` to the input of machine `to`.
+    /// A machine connected to several others sends each output to all of them.
+    pub fn connect(&mut self, from: usize, to: usize) -> &mut Self {
+        self.links[from].push(to);
+        self
+    }
+
+    /// Queue inputs for a machine, ahead of anything sent by other machines.
+    pub fn seed(&mut self, machine: usize, inputs: &[i64]) -> &mut Self {
+        self.seeds[machine].extend_from_slice(inputs);
+        self
+    }
+
+    /// Collect the outputs of a machine into the result of `run`.
+    pub fn tap(&mut self, machine: usize) -> &mut Self {
+        self.taps[machine] = true;
+        self
+    }
+
+    /// Limit the instructions each machine may execute.
+    pub fn budget(&mut self, budget: Budget) -> &mut Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Run all machines in turn in the current thread until they all halt, and
+    /// return the outputs of the tapped machines in the order they were
+    /// produced. A deadlock is reported instead of blocking forever.
+    pub fn run(self) -> Result<Vec<i64>, NetworkError> {
+        let machines = self
+            .programs
+            .into_iter()
+            .zip(&self.seeds)
+            .map(|(program, seed)| {
+                let mut computer = IntCodeComputer::new(program, NoIO {});
+                for &val in seed {
+                    computer.push_input(val);
+                }
+                computer
+            })
+            .collect();
+
+        let mut scheduler = Scheduler::new(machines);
+        if let Some(budget) = self.budget {
+            scheduler.budget(budget);
+        }
+
+        let (links, taps) = (self.links, self.taps);
+        let mut tapped = vec![];
+        scheduler.run(|machines, machine, val| {
+            for &to in &links[machine] {
+                machines[to].push_input(val);
+            }
+            if taps[machine] {
+                tapped.push(val);
+            }
+        })?;
+        Ok(tapped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network() {
+        // adds its two inputs
+        let adder = vec![3, 11, 3, 12, 1, 11, 12, 11, 4, 11, 99, 0, 0];
+        // outputs twice its input
+        let doubler = vec![3, 9, 1002, 9, 2, 9, 4, 9, 99, 0];
+
+        let mut network = Network::new();
+        let a = network.add_machine(doubler.clone());
+        let b = network.add_machine(doubler);
+        let c = network.add_machine(adder);
+        network
+            .seed(a, &[3])
+            .connect(a, b)
+            .connect(a, c)
+            .connect(b, c)
+            .tap(b)
+            .tap(c);
+        assert_eq!(network.run().unwrap(), vec![12, 18]);
+
+        let mut network = Network::new();
+        let a = network.add_machine(vec![1105, 1, 0]);
+        network.budget(Budget::instructions(100));
+        let error = network.run().unwrap_err();
+        assert_eq!(error.machine, a);
+        assert!(matches!(error.kind, NetworkErrorKind::BudgetExhausted));
+    }
+}
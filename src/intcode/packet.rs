@@ -0,0 +1,254 @@
+use super::network::{NetworkError, NetworkErrorKind};
+use super::scheduler::Scheduler;
+use super::{IntCodeComputer, Status, IO};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::io;
+
+/// Address of the NAT, which receives packets sent outside the network.
+pub const NAT_ADDRESS: i64 = 255;
+
+/// Value read by a machine whose packet queue is empty.
+const NO_PACKET: i64 = -1;
+
+/// Number of reads of an empty queue in a row after which a machine is idle.
+const IDLE_READS: u32 = 2;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Packet {
+    pub x: i64,
+    pub y: i64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Event {
+    /// The NAT received a packet.
+    NatReceived(Packet),
+    /// The network was idle, and the NAT sent its last packet to machine 0.
+    NatSent(Packet),
+}
+
+/// Network interface of a machine: inputs are never blocking, a machine with
+/// no packet waiting reads `NO_PACKET` instead.
+#[derive(Clone, Debug, Default)]
+struct PacketIO {
+    input: VecDeque<i64>,
+    empty_reads: u32,
+}
+
+impl IO for PacketIO {
+    fn get(&mut self) -> io::Result<i64> {
+        match self.input.pop_front() {
+            Some(val) => {
+                self.empty_reads = 0;
+                Ok(val)
+            }
+            None => {
+                self.empty_reads += 1;
+                Ok(NO_PACKET)
+            }
+        }
+    }
+
+    fn put(&mut self, _: i64) -> io::Result<()> {
+        self.empty_reads = 0;
+        Ok(())
+    }
+}
+
+/// Machines exchanging `(address, X, Y)` packets, with a NAT watching over
+/// the network.
+pub struct PacketNetwork {
+    scheduler: Scheduler<PacketIO>,
+    executed: Vec<u64>,
+    /// Values of the packet each machine is sending.
+    partial: Vec<Vec<i64>>,
+    nat: Option<Packet>,
+    events: VecDeque<Event>,
+}
+
+impl PacketNetwork {
+    /// Boot `size` machines running `program`, each given its address.
+    pub fn new(program: &[i64], size: usize) -> Self {
+        let machines = (0..size)
+            .map(|address| {
+                let mut io = PacketIO::default();
+                io.input.push_back(address as i64);
+                IntCodeComputer::new(program.to_vec(), io)
+            })
+            .collect();
+
+        Self {
+            scheduler: Scheduler::new(machines),
+            executed: vec![0; size],
+            partial: vec![vec![]; size],
+            nat: None,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Run the network until the NAT receives or sends a packet.
+    ///
+    /// Machines are expected to run forever: one halting, or sending a packet
+    /// to an address which is neither a machine nor the NAT, is an error.
+    pub fn next_event(&mut self) -> Result<Event, NetworkError> {
+        while self.events.is_empty() {
+            self.round()?;
+        }
+        Ok(self.events.pop_front().unwrap())
+    }
+
+    /// Give each machine a turn, then deliver the packets they sent.
+    fn round(&mut self) -> Result<(), NetworkError> {
+        for machine in 0..self.partial.len() {
+            let mut outputs = vec![];
+            let status = self
+                .scheduler
+                .turn(machine, &mut self.executed[machine], &mut outputs)?;
+
+            for val in outputs {
+                self.partial[machine].push(val);
+                if let [address, x, y] = self.partial[machine][..] {
+                    self.partial[machine].clear();
+                    self.send(machine, address, Packet { x, y })?;
+                }
+            }
+
+            if status == Status::Halted {
+                return Err(NetworkError {
+                    machine,
+                    kind: NetworkErrorKind::Halted,
+                });
+            }
+        }
+
+        if (0..self.partial.len()).all(|machine| self.is_idle(machine)) {
+            match self.nat {
+                Some(packet) => {
+                    self.send(NAT_ADDRESS as usize, 0, packet)?;
+                    self.events.push_back(Event::NatSent(packet));
+                }
+                None => {
+                    return Err(NetworkError {
+                        machine: 0,
+                        kind: NetworkErrorKind::Deadlock((0..self.partial.len()).collect()),
+                    })
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_idle(&self, machine: usize) -> bool {
+        let io = &self.scheduler.machines[machine].io;
+        io.input.is_empty() && self.partial[machine].is_empty() && io.empty_reads >= IDLE_READS
+    }
+
+    /// Deliver a packet sent by `sender`, the NAT sending from `NAT_ADDRESS`.
+    fn send(&mut self, sender: usize, address: i64, packet: Packet) -> Result<(), NetworkError> {
+        if address == NAT_ADDRESS {
+            self.nat = Some(packet);
+            self.events.push_back(Event::NatReceived(packet));
+            return Ok(());
+        }
+
+        let machines = &mut self.scheduler.machines;
+        match usize::try_from(address)
+            .ok()
+            .and_then(|a| machines.get_mut(a))
+        {
+            Some(machine) => {
+                machine.io.input.extend(&[packet.x, packet.y]);
+                Ok(())
+            }
+            None => Err(NetworkError {
+                machine: sender,
+                kind: NetworkErrorKind::InvalidAddress(address),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    #[test]
+    fn test_packet_network() {
+        // machine 0 sends a packet down a chain of 3 machines, the last one
+        // forwards it to the NAT
+        let program = assemble(
+            "
+                    in   [addr]
+                    jit  [addr], #wait
+                    out  #1
+                    out  #7
+                    out  #0
+            wait:   in   [x]
+                    eq   [x], #-1, [tmp]
+                    jit  [tmp], #wait
+                    in   [y]
+                    add  [addr], #1, [dest]
+                    eq   [dest], #3, [tmp]
+                    jif  [tmp], #send
+                    add  #255, #0, [dest]
+            send:   out  [dest]
+                    out  [x]
+                    out  [y]
+                    jit  #1, #wait
+            addr:   data 0
+            x:      data 0
+            y:      data 0
+            dest:   data 0
+            tmp:    data 0
+            ",
+        )
+        .unwrap();
+
+        let mut network = PacketNetwork::new(&program, 3);
+        let packet = Packet { x: 7, y: 0 };
+        assert_eq!(network.next_event(), Ok(Event::NatReceived(packet)));
+        assert_eq!(network.next_event(), Ok(Event::NatSent(packet)));
+        assert_eq!(network.next_event(), Ok(Event::NatReceived(packet)));
+        assert_eq!(network.next_event(), Ok(Event::NatSent(packet)));
+    }
+
+    #[test]
+    fn test_packet_network_errors() {
+        // every machine halts once it read its address
+        let mut network = PacketNetwork::new(&[3, 5, 99, 0, 0, 0], 2);
+        assert_eq!(
+            network.next_event(),
+            Err(NetworkError {
+                machine: 0,
+                kind: NetworkErrorKind::Halted
+            })
+        );
+
+        // machine 1 sends a packet to address 2, then waits forever
+        let program = assemble(
+            "
+                    in   [addr]
+                    jif  [addr], #wait
+                    out  #2
+                    out  #7
+                    out  #0
+            wait:   in   [x]
+                    jit  #1, #wait
+            addr:   data 0
+            x:      data 0
+            ",
+        )
+        .unwrap();
+        let mut network = PacketNetwork::new(&program, 2);
+        assert_eq!(
+            network.next_event(),
+            Err(NetworkError {
+                machine: 1,
+                kind: NetworkErrorKind::InvalidAddress(2)
+            })
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use super::{
+    disasm::{disassemble, Item, Line},
+    word::Word,
+    Opcode, Tracer,
+};
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+/// Number of entries shown in each section of a report.
+const REPORT_ENTRIES: usize = 10;
+
+/// Tracer counting executed instructions and taken backward jumps.
+#[derive(Clone, Debug, Default)]
+pub struct Profiler {
+    /// Executions of each instruction address, kept sparse as a program
+    /// may jump anywhere in memory.
+    counts: HashMap<usize, u64>,
+    opcodes: HashMap<Opcode, u64>,
+    backward_jumps: HashMap<(usize, usize), u64>,
+}
+
+impl<W: Word> Tracer<W> for Profiler {
+    fn instruction(&mut self, ip: usize, opcode: Opcode, operands: &[W]) {
+        *self.counts.entry(ip).or_insert(0) += 1;
+        *self.opcodes.entry(opcode).or_insert(0) += 1;
+
+        let taken = match opcode {
+            Opcode::Jit => !operands[0].is_zero(),
+            Opcode::Jif => operands[0].is_zero(),
+            _ => false,
+        };
+        if taken {
+            if let Some(target) = operands[1].to_usize().filter(|&target| target <= ip) {
+                *self.backward_jumps.entry((target, ip)).or_insert(0) += 1;
+            }
+        }
+    }
+}
+
+impl Profiler {
+    /// Build a report, using `program` to list the instructions involved.
+    pub fn report(&self, program: &[i64]) -> Report {
+        let mut opcodes = self
+            .opcodes
+            .iter()
+            .map(|(&opcode, &count)| (opcode, count))
+            .collect::<Vec<_>>();
+        opcodes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.to_string().cmp(&b.0.to_string())));
+
+        let listing = disassemble(program);
+
+        let mut hot_spots = listing
+            .iter()
+            .map(|line| (line.clone(), self.count(line.address)))
+            .filter(|(_, count)| *count > 0)
+            .collect::<Vec<_>>();
+        hot_spots.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.address.cmp(&b.0.address)));
+
+        let mut loops = self
+            .backward_jumps
+            .iter()
+            .map(|(&(start, end), &iterations)| Loop {
+                start,
+                end,
+                iterations,
+                executed: self
+                    .counts
+                    .iter()
+                    .filter(|(addr, _)| (start..=end).contains(*addr))
+                    .map(|(_, count)| count)
+                    .sum(),
+            })
+            .collect::<Vec<_>>();
+        loops.sort_by(|a, b| b.executed.cmp(&a.executed).then(a.start.cmp(&b.start)));
+
+        let mut unexecuted: Vec<(usize, usize)> = vec![];
+        for line in listing.iter() {
+            if let Item::Data(_) = line.item {
+                continue;
+            }
+            if self.counts.contains_key(&line.address) {
+                continue;
+            }
+            let end = line.address + line.item.size();
+            match unexecuted.last_mut() {
+                Some(range) if range.1 == line.address => range.1 = end,
+                _ => unexecuted.push((line.address, end)),
+            }
+        }
+
+        Report {
+            total: self.counts.values().sum(),
+            opcodes,
+            hot_spots,
+            loops,
+            unexecuted,
+        }
+    }
+
+    /// Number of times the instruction at `address` was executed.
+    pub fn count(&self, address: usize) -> u64 {
+        self.counts.get(&address).cloned().unwrap_or(0)
+    }
+}
+
+/// Loop detected from a backward jump, from its target to the jump itself.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Loop {
+    pub start: usize,
+    pub end: usize,
+    pub iterations: u64,
+    /// Number of instructions executed within the loop addresses.
+    pub executed: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Report {
+    pub total: u64,
+    pub opcodes: Vec<(Opcode, u64)>,
+    pub hot_spots: Vec<(Line, u64)>,
+    pub loops: Vec<Loop>,
+    /// Ranges of code addresses, end excluded, which were never executed.
+    /// Code is told apart from data by a linear disassembly of the program.
+    pub unexecuted: Vec<(usize, usize)>,
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Instructions executed: {}", self.total)?;
+
+        writeln!(f, "\nOpcodes:")?;
+        for (opcode, count) in self.opcodes.iter() {
+            writeln!(f, "{:>12}  {}", count, opcode)?;
+        }
+
+        writeln!(f, "\nHot spots:")?;
+        for (line, count) in self.hot_spots.iter().take(REPORT_ENTRIES) {
+            writeln!(f, "{:>12}  {}", count, line)?;
+        }
+
+        writeln!(f, "\nHot loops:")?;
+        for l in self.loops.iter().take(REPORT_ENTRIES) {
+            writeln!(
+                f,
+                "{:>12}  {:04}-{:04}, {} iterations",
+                l.executed, l.start, l.end, l.iterations
+            )?;
+        }
+
+        writeln!(f, "\nNever executed:")?;
+        for (start, end) in self.unexecuted.iter() {
+            writeln!(f, "{:>12}  {:04}-{:04}", "", start, end - 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{asm::assemble, IntCodeComputer, NoIO};
+
+    #[test]
+    fn test_profiler() {
+        let program = assemble(
+            "
+                    jit  #0, #skip
+            loop:   add  [counter], #-1, [counter]
+                    jit  [counter], #loop
+                    halt
+            skip:   out  #1
+                    halt
+            counter: data 3
+            ",
+        )
+        .unwrap();
+        let mut computer =
+            IntCodeComputer::new(program.clone(), NoIO {}).with_tracer(Profiler::default());
+        computer.run().unwrap();
+
+        let report = computer.tracer.report(&program);
+        assert_eq!(report.total, 8);
+        assert_eq!(
+            report.opcodes,
+            vec![(Opcode::Jit, 4), (Opcode::Add, 3), (Opcode::Halt, 1)]
+        );
+        assert_eq!(report.hot_spots[0].0.address, 3);
+        assert_eq!(
+            report.loops,
+            vec![Loop {
+                start: 3,
+                end: 7,
+                iterations: 2,
+                executed: 6,
+            }]
+        );
+        assert_eq!(report.unexecuted, vec![(11, 14)]);
+
+        // write a halt far away and jump to it
+        let program = vec![1101, 99, 0, 1 << 40, 1106, 0, 1 << 40];
+        let mut computer =
+            IntCodeComputer::new(program.clone(), NoIO {}).with_tracer(Profiler::default());
+        computer.run().unwrap();
+        assert_eq!(computer.tracer.count(1 << 40), 1);
+        assert_eq!(computer.tracer.report(&program).total, 3);
+    }
+}
@@ -0,0 +1,286 @@
+use super::IO;
+use std::collections::VecDeque;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, BufRead, ErrorKind, Write};
+use std::str::FromStr;
+
+/// A value exchanged between a program and its IO.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Event {
+    Input(i64),
+    Output(i64),
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Input(v) => write!(f, "in {}", v),
+            Event::Output(v) => write!(f, "out {}", v),
+        }
+    }
+}
+
+impl FromStr for Event {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let event = match (words.next(), words.next().map(str::parse)) {
+            (Some("in"), Some(Ok(v))) => Event::Input(v),
+            (Some("out"), Some(Ok(v))) => Event::Output(v),
+            _ => return Err(format!("Invalid event: {}", s)),
+        };
+        match words.next() {
+            None => Ok(event),
+            Some(_) => Err(format!("Invalid event: {}", s)),
+        }
+    }
+}
+
+/// IO wrapper writing every value going through `io` to `log`, one numbered
+/// event per line, such as `12 out 42`. Inputs queued with `push_input` are
+/// recorded too, so that a replay feeds them back through the IO.
+pub struct RecordIO<T, F>
+where
+    T: IO,
+    F: Write,
+{
+    pub io: T,
+    log: F,
+    step: u64,
+}
+
+impl<T, F> RecordIO<T, F>
+where
+    T: IO,
+    F: Write,
+{
+    pub fn new(io: T, log: F) -> Self {
+        Self { io, log, step: 0 }
+    }
+
+    fn record(&mut self, event: Event) -> io::Result<()> {
+        writeln!(self.log, "{} {}", self.step, event)?;
+        self.step += 1;
+        Ok(())
+    }
+}
+
+impl<T, F> IO for RecordIO<T, F>
+where
+    T: IO,
+    F: Write,
+{
+    fn get(&mut self) -> io::Result<i64> {
+        let val = self.io.get()?;
+        self.record(Event::Input(val))?;
+        Ok(val)
+    }
+
+    fn put(&mut self, val: i64) -> io::Result<()> {
+        self.io.put(val)?;
+        self.record(Event::Output(val))
+    }
+
+    fn queued(&mut self, val: &i64) -> io::Result<()> {
+        self.io.queued(val)?;
+        self.record(Event::Input(*val))
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum ReplayErrorKind {
+    /// The program asked for an input where the recording has an output.
+    UnexpectedInput,
+    /// The program produced an output where the recording has an input, or
+    /// after its end.
+    UnexpectedOutput(i64),
+    OutputMismatch {
+        expected: i64,
+        found: i64,
+    },
+    /// The program stopped before the end of the recording.
+    Incomplete,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ReplayError {
+    pub step: u64,
+    pub kind: ReplayErrorKind,
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "step {}: ", self.step)?;
+        match self.kind {
+            ReplayErrorKind::UnexpectedInput => write!(f, "program asked for an input"),
+            ReplayErrorKind::UnexpectedOutput(v) => write!(f, "unexpected output {}", v),
+            ReplayErrorKind::OutputMismatch { expected, found } => {
+                write!(f, "expected output {}, found {}", expected, found)
+            }
+            ReplayErrorKind::Incomplete => write!(f, "program stopped before the end"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// IO feeding the inputs of a recording back to a program, and checking that
+/// its outputs match the recorded ones.
+pub struct ReplayIO {
+    events: VecDeque<Event>,
+    step: u64,
+    error: Option<ReplayError>,
+}
+
+impl ReplayIO {
+    /// Load a recording written by `RecordIO`.
+    pub fn load<R: BufRead>(r: R) -> io::Result<Self> {
+        let mut events = VecDeque::new();
+        for line in r.lines() {
+            let line = line?;
+            let event = line
+                .split_once(' ')
+                .and_then(|(_, event)| event.parse().ok())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("invalid recording line: {}", line),
+                    )
+                })?;
+            events.push_back(event);
+        }
+
+        Ok(Self {
+            events,
+            step: 0,
+            error: None,
+        })
+    }
+
+    /// Check that the program went through the whole recording without a
+    /// mismatch.
+    pub fn finish(self) -> Result<(), ReplayError> {
+        match self.error {
+            Some(e) => Err(e),
+            None if !self.events.is_empty() => Err(ReplayError {
+                step: self.step,
+                kind: ReplayErrorKind::Incomplete,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn fail(&mut self, kind: ReplayErrorKind) -> io::Error {
+        self.error = Some(ReplayError {
+            step: self.step,
+            kind,
+        });
+        io::Error::from(ErrorKind::InvalidData)
+    }
+}
+
+impl IO for ReplayIO {
+    fn get(&mut self) -> io::Result<i64> {
+        match self.events.front() {
+            Some(&Event::Input(v)) => {
+                self.events.pop_front();
+                self.step += 1;
+                Ok(v)
+            }
+            Some(Event::Output(_)) => Err(self.fail(ReplayErrorKind::UnexpectedInput)),
+            None => Err(io::Error::from(ErrorKind::UnexpectedEof)),
+        }
+    }
+
+    fn put(&mut self, val: i64) -> io::Result<()> {
+        match self.events.front() {
+            Some(&Event::Output(v)) if v == val => {
+                self.events.pop_front();
+                self.step += 1;
+                Ok(())
+            }
+            Some(&Event::Output(expected)) => Err(self.fail(ReplayErrorKind::OutputMismatch {
+                expected,
+                found: val,
+            })),
+            _ => Err(self.fail(ReplayErrorKind::UnexpectedOutput(val))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{IntCodeComputer, NoIO, QueueIO, Status};
+
+    fn replay(program: &[i64], recording: &[u8]) -> Result<(), ReplayError> {
+        let io = ReplayIO::load(recording).unwrap();
+        let mut computer = IntCodeComputer::new(program.to_vec(), io);
+        let _ = computer.run();
+        computer.io.finish()
+    }
+
+    #[test]
+    fn test_record_replay() {
+        // outputs twice each of its inputs until it reads 0
+        let program = vec![
+            3, 15, 1006, 15, 14, 102, 2, 15, 15, 4, 15, 1105, 1, 0, 99, 0,
+        ];
+        let mut log = vec![];
+        let io = RecordIO::new(QueueIO::new(&[3, 4, 0]), &mut log);
+        let mut computer = IntCodeComputer::new(program.clone(), io);
+        assert_eq!(computer.run(), Ok(Status::Halted));
+        assert_eq!(computer.io.io.drain_outputs(), vec![6, 8]);
+        drop(computer);
+
+        let recording = String::from_utf8(log).unwrap();
+        assert_eq!(recording, "0 in 3\n1 out 6\n2 in 4\n3 out 8\n4 in 0\n");
+        assert_eq!(replay(&program, recording.as_bytes()), Ok(()));
+
+        assert_eq!(
+            replay(&program, b"0 in 3\n1 out 7\n"),
+            Err(ReplayError {
+                step: 1,
+                kind: ReplayErrorKind::OutputMismatch {
+                    expected: 7,
+                    found: 6
+                }
+            })
+        );
+        assert_eq!(
+            replay(&program, b"0 in 3\n1 in 4\n"),
+            Err(ReplayError {
+                step: 1,
+                kind: ReplayErrorKind::UnexpectedOutput(6)
+            })
+        );
+        assert_eq!(
+            replay(&program, b"0 in 0\n1 out 0\n"),
+            Err(ReplayError {
+                step: 1,
+                kind: ReplayErrorKind::Incomplete
+            })
+        );
+        assert!(ReplayIO::load(&b"0 out x\n"[..]).is_err());
+    }
+
+    #[test]
+    fn test_record_queued_inputs() {
+        let program = vec![
+            3, 15, 1006, 15, 14, 102, 2, 15, 15, 4, 15, 1105, 1, 0, 99, 0,
+        ];
+        let mut log = vec![];
+        let mut computer = IntCodeComputer::new(program.clone(), RecordIO::new(NoIO {}, &mut log));
+        for input in [3, 4, 0] {
+            assert_eq!(computer.run(), Ok(Status::WaitingForInput));
+            computer.push_input(input);
+        }
+        assert_eq!(computer.run(), Ok(Status::Halted));
+        drop(computer);
+
+        let recording = String::from_utf8(log).unwrap();
+        assert_eq!(recording, "0 in 3\n1 out 6\n2 in 4\n3 out 8\n4 in 0\n");
+        assert_eq!(replay(&program, recording.as_bytes()), Ok(()));
+    }
+}
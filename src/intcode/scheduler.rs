@@ -0,0 +1,170 @@
+use super::network::{NetworkError, NetworkErrorKind};
+use super::{Budget, IntCodeComputer, Status, IO};
+use std::time::Instant;
+
+/// Instructions a machine executes before the next one gets its turn.
+const TIME_SLICE: u64 = 1000;
+
+/// Runs several machines in turn in the current thread, so that the same
+/// programs and inputs always lead to the same results.
+pub struct Scheduler<T>
+where
+    T: IO,
+{
+    pub machines: Vec<IntCodeComputer<T>>,
+    budget: Budget,
+}
+
+impl<T> Scheduler<T>
+where
+    T: IO,
+{
+    pub fn new(machines: Vec<IntCodeComputer<T>>) -> Self {
+        Self {
+            machines,
+            budget: Budget::default(),
+        }
+    }
+
+    /// Limit the instructions each machine may execute, and the time all of
+    /// them may run for.
+    pub fn budget(&mut self, budget: Budget) -> &mut Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Run all machines until they halt. `route` is called with the id of a
+    /// machine and each value it outputs, and may queue inputs for any machine.
+    ///
+    /// Fails with a deadlock when no machine can make progress because all of
+    /// those still running wait for an input.
+    pub fn run<F>(&mut self, mut route: F) -> Result<(), NetworkError>
+    where
+        F: FnMut(&mut [IntCodeComputer<T>], usize, i64),
+    {
+        let deadline = self.budget.timeout.map(|timeout| Instant::now() + timeout);
+        let mut executed = vec![0; self.machines.len()];
+        let mut halted = vec![false; self.machines.len()];
+
+        loop {
+            let mut progress = false;
+            let mut waiting = vec![];
+
+            for machine in 0..self.machines.len() {
+                if halted[machine] {
+                    continue;
+                }
+
+                let mut outputs = vec![];
+                let start = executed[machine];
+                let status = self.turn(machine, &mut executed[machine], &mut outputs)?;
+                progress |= executed[machine] > start;
+                for val in outputs {
+                    route(&mut self.machines, machine, val);
+                }
+
+                match status {
+                    Status::Halted => halted[machine] = true,
+                    Status::WaitingForInput => waiting.push(machine),
+                    _ => {}
+                }
+            }
+
+            let running = match halted.iter().position(|&h| !h) {
+                Some(machine) => machine,
+                None => return Ok(()),
+            };
+            // a round without progress halted a machine, unless some wait
+            if let (false, Some(&machine)) = (progress, waiting.first()) {
+                return Err(NetworkError {
+                    machine,
+                    kind: NetworkErrorKind::Deadlock(waiting),
+                });
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(NetworkError {
+                    machine: running,
+                    kind: NetworkErrorKind::BudgetExhausted,
+                });
+            }
+        }
+    }
+
+    /// Run a machine for up to a time slice, collecting its outputs.
+    /// `executed` counts the instructions it ran over all its turns.
+    pub fn turn(
+        &mut self,
+        machine: usize,
+        executed: &mut u64,
+        outputs: &mut Vec<i64>,
+    ) -> Result<Status, NetworkError> {
+        let computer = &mut self.machines[machine];
+        let end = *executed + TIME_SLICE;
+
+        while *executed < end {
+            if self.budget.instructions.is_some_and(|max| *executed >= max) {
+                return Err(NetworkError {
+                    machine,
+                    kind: NetworkErrorKind::BudgetExhausted,
+                });
+            }
+
+            let status = computer.step().map_err(|e| NetworkError {
+                machine,
+                kind: NetworkErrorKind::Fault(e),
+            })?;
+            match status {
+                // watchpoints are for debugging single machines, keep going
+                Status::Running | Status::Watchpoint(_) => {}
+                Status::Output(val) => outputs.push(val),
+                status => return Ok(status),
+            }
+            *executed += 1;
+        }
+
+        Ok(Status::Running)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::watch::{Access, Action};
+    use crate::intcode::NoIO;
+
+    #[test]
+    fn test_scheduler() {
+        // outputs its input plus one, forever
+        let increment = vec![3, 11, 1001, 11, 1, 11, 4, 11, 1105, 1, 0, 0];
+        let mut machines = vec![
+            IntCodeComputer::new(increment.clone(), NoIO {}),
+            IntCodeComputer::new(increment.clone(), NoIO {}),
+        ];
+        machines[0].push_input(0);
+
+        // machines pass a counter to each other until it reaches 10
+        let mut outputs = vec![];
+        let result = Scheduler::new(machines).run(|machines, machine, val| {
+            outputs.push((machine, val));
+            if val < 10 {
+                machines[1 - machine].push_input(val);
+            }
+        });
+        assert_eq!(outputs.len(), 10);
+        assert_eq!(outputs.last(), Some(&(1, 10)));
+
+        let error = result.unwrap_err();
+        assert_eq!(error.machine, 0);
+        assert!(
+            matches!(error.kind, NetworkErrorKind::Deadlock(ref waiting) if waiting == &[0, 1])
+        );
+
+        // pausing on a watchpoint is progress, not a deadlock
+        let mut computer = IntCodeComputer::new(vec![1101, 1, 1, 7, 4, 7, 99, 0], NoIO {});
+        computer.watch(7, Access::Write, Action::Pause);
+        let mut outputs = vec![];
+        let result = Scheduler::new(vec![computer]).run(|_, _, val| outputs.push(val));
+        assert_eq!(result, Ok(()));
+        assert_eq!(outputs, vec![2]);
+    }
+}
@@ -0,0 +1,422 @@
+use super::{decode_instruction, FaultKind, Opcode, ParameterMode};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display, Formatter};
+use std::ops::RangeInclusive;
+
+/// Instructions executed before giving up on a program which does not halt.
+const STEP_LIMIT: usize = 1_000_000;
+
+/// Polynomial over the variables of a symbolic execution, with the same
+/// wrapping arithmetic as the machine.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Expr {
+    /// Coefficient of each product of variables, given as sorted indices.
+    terms: BTreeMap<Vec<usize>, i64>,
+}
+
+impl Expr {
+    pub fn constant(val: i64) -> Self {
+        let mut expr = Self::default();
+        expr.insert(vec![], val);
+        expr
+    }
+
+    pub fn var(idx: usize) -> Self {
+        let mut expr = Self::default();
+        expr.insert(vec![idx], 1);
+        expr
+    }
+
+    /// Value of the expression if it does not depend on any variable.
+    pub fn as_constant(&self) -> Option<i64> {
+        match self.terms.iter().next() {
+            None => Some(0),
+            Some((vars, &c)) if vars.is_empty() && self.terms.len() == 1 => Some(c),
+            _ => None,
+        }
+    }
+
+    pub fn eval(&self, values: &[i64]) -> i64 {
+        self.terms.iter().fold(0i64, |sum, (vars, &c)| {
+            let term = vars
+                .iter()
+                .fold(c, |product, &var| product.wrapping_mul(values[var]));
+            sum.wrapping_add(term)
+        })
+    }
+
+    fn add(&self, other: &Expr) -> Expr {
+        let mut sum = self.clone();
+        for (vars, &c) in &other.terms {
+            sum.insert(vars.clone(), c);
+        }
+        sum
+    }
+
+    fn mul(&self, other: &Expr) -> Expr {
+        let mut product = Expr::default();
+        for (a_vars, &a) in &self.terms {
+            for (b_vars, &b) in &other.terms {
+                let mut vars = a_vars.iter().chain(b_vars).cloned().collect::<Vec<_>>();
+                vars.sort_unstable();
+                product.insert(vars, a.wrapping_mul(b));
+            }
+        }
+        product
+    }
+
+    fn insert(&mut self, vars: Vec<usize>, c: i64) {
+        let coefficient = self.terms.entry(vars.clone()).or_insert(0);
+        *coefficient = coefficient.wrapping_add(c);
+        if *coefficient == 0 {
+            self.terms.remove(&vars);
+        }
+    }
+
+    /// With the variables before `var` set to `values`, write the expression
+    /// as `a * var + b`, if it is linear in `var`.
+    fn linear_in(&self, var: usize, values: &[i64]) -> Option<(i64, i64)> {
+        let (mut a, mut b) = (0i64, 0i64);
+        for (vars, &c) in &self.terms {
+            let mut term = c;
+            let mut degree = 0;
+            for &v in vars {
+                if v == var {
+                    degree += 1;
+                } else {
+                    term = term.wrapping_mul(values[v]);
+                }
+            }
+            match degree {
+                0 => b = b.wrapping_add(term),
+                1 => a = a.wrapping_add(term),
+                _ => return None,
+            }
+        }
+        Some((a, b))
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+        // the constant term comes first in the map, but is written last
+        let mut terms = self
+            .terms
+            .iter()
+            .map(|(vars, c)| {
+                let vars = vars.iter().map(|v| format!("x{}", v)).collect::<Vec<_>>();
+                match (c, vars.is_empty()) {
+                    (c, true) => c.to_string(),
+                    (1, false) => vars.join("*"),
+                    (c, false) => format!("{}*{}", c, vars.join("*")),
+                }
+            })
+            .collect::<Vec<_>>();
+        if self.terms.contains_key(&vec![]) {
+            terms.rotate_left(1);
+        }
+        write!(f, "{}", terms.join(" + "))
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum SymbolicErrorKind {
+    Fault(FaultKind),
+    /// The instruction to execute depends on the variables.
+    SymbolicInstruction,
+    /// An address written to or jumped to depends on the variables.
+    SymbolicAddress,
+    /// A conditional jump depends on the variables.
+    SymbolicCondition,
+    /// The program reads or writes values, which is not supported.
+    UnsupportedOpcode(Opcode),
+    /// The result was read from an address which depends on the variables.
+    UnknownResult,
+    StepLimit,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct SymbolicError {
+    pub ip: usize,
+    pub kind: SymbolicErrorKind,
+}
+
+impl Display for SymbolicError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let reason = match &self.kind {
+            SymbolicErrorKind::Fault(kind) => kind.to_string(),
+            SymbolicErrorKind::SymbolicInstruction => String::from("symbolic instruction"),
+            SymbolicErrorKind::SymbolicAddress => String::from("symbolic address"),
+            SymbolicErrorKind::SymbolicCondition => String::from("branch on a symbolic value"),
+            SymbolicErrorKind::UnsupportedOpcode(opcode) => {
+                format!("unsupported opcode {}", opcode)
+            }
+            SymbolicErrorKind::UnknownResult => String::from("result read from a symbolic address"),
+            SymbolicErrorKind::StepLimit => String::from("program did not halt"),
+        };
+        write!(f, "{} at ip {}", reason, self.ip)
+    }
+}
+
+impl std::error::Error for SymbolicError {}
+
+/// Execute a program without IO where the cells at `variables` hold unknowns
+/// `x0, x1...`, and return the expression held by the cell `result` once it
+/// halts. The program may loop, but only on values not depending on the
+/// variables.
+///
+/// Values read from addresses depending on the variables are unknown, which
+/// is only an error when they are used to take a decision or as the result.
+pub fn execute(program: &[i64], variables: &[usize], result: usize) -> Result<Expr, SymbolicError> {
+    // None stands for an unknown value, cells missing from the map hold 0
+    let mut memory = program
+        .iter()
+        .enumerate()
+        .map(|(address, &word)| (address, Some(Expr::constant(word))))
+        .collect::<HashMap<_, _>>();
+    for (idx, &address) in variables.iter().enumerate() {
+        write(&mut memory, address, Some(Expr::var(idx)));
+    }
+
+    let mut ip = 0;
+    let mut relative_base = 0;
+    for _ in 0..STEP_LIMIT {
+        let error = |kind| SymbolicError { ip, kind };
+        let instruction = read(&memory, ip)
+            .as_ref()
+            .and_then(Expr::as_constant)
+            .ok_or_else(|| error(SymbolicErrorKind::SymbolicInstruction))?;
+        let (opcode, modes) =
+            decode_instruction(instruction).map_err(|e| error(SymbolicErrorKind::Fault(e)))?;
+
+        // address of each parameter, when it does not depend on the variables
+        let addresses = (0..opcode.arity())
+            .map(|idx| {
+                let param = ip + idx + 1;
+                let word = read(&memory, param).as_ref().and_then(Expr::as_constant);
+                let address = match modes[idx] {
+                    ParameterMode::Position => word,
+                    ParameterMode::Immediate => return Ok(Some(param)),
+                    ParameterMode::Relative => word
+                        .map(|w| {
+                            w.checked_add(relative_base)
+                                .ok_or(FaultKind::WordOutOfRange)
+                        })
+                        .transpose()
+                        .map_err(|e| error(SymbolicErrorKind::Fault(e)))?,
+                };
+                match address {
+                    Some(a) if a < 0 => Err(error(SymbolicErrorKind::Fault(
+                        FaultKind::NegativeAddress(a),
+                    ))),
+                    a => Ok(a.map(|a| a as usize)),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let value = |idx: usize| addresses[idx].and_then(|a| read(&memory, a).clone());
+        let constant = |idx: usize| value(idx).as_ref().and_then(Expr::as_constant);
+        let dest = |idx: usize| match modes[idx] {
+            ParameterMode::Immediate => Err(error(SymbolicErrorKind::Fault(
+                FaultKind::ImmediateDestination,
+            ))),
+            _ => addresses[idx].ok_or_else(|| error(SymbolicErrorKind::SymbolicAddress)),
+        };
+
+        match opcode {
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => {
+                let val = match (opcode, value(0), value(1)) {
+                    (Opcode::Add, Some(a), Some(b)) => Some(a.add(&b)),
+                    (Opcode::Mul, Some(a), Some(b)) => Some(a.mul(&b)),
+                    (Opcode::Lt, ..) => constant(0)
+                        .zip(constant(1))
+                        .map(|(a, b)| Expr::constant((a < b) as i64)),
+                    (Opcode::Eq, ..) => constant(0)
+                        .zip(constant(1))
+                        .map(|(a, b)| Expr::constant((a == b) as i64)),
+                    _ => None,
+                };
+                let address = dest(2)?;
+                write(&mut memory, address, val);
+                ip += 4;
+            }
+            Opcode::Jit | Opcode::Jif => {
+                let condition =
+                    constant(0).ok_or_else(|| error(SymbolicErrorKind::SymbolicCondition))?;
+                if (condition != 0) == (opcode == Opcode::Jit) {
+                    let target =
+                        constant(1).ok_or_else(|| error(SymbolicErrorKind::SymbolicAddress))?;
+                    if target < 0 {
+                        return Err(error(SymbolicErrorKind::Fault(FaultKind::NegativeAddress(
+                            target,
+                        ))));
+                    }
+                    ip = target as usize;
+                } else {
+                    ip += 3;
+                }
+            }
+            Opcode::Arb => {
+                relative_base = constant(0)
+                    .ok_or_else(|| error(SymbolicErrorKind::SymbolicAddress))?
+                    .checked_add(relative_base)
+                    .ok_or_else(|| error(SymbolicErrorKind::Fault(FaultKind::WordOutOfRange)))?;
+                ip += 2;
+            }
+            Opcode::In | Opcode::Out => {
+                return Err(error(SymbolicErrorKind::UnsupportedOpcode(opcode)))
+            }
+            Opcode::Halt => {
+                return read(&memory, result)
+                    .clone()
+                    .ok_or_else(|| error(SymbolicErrorKind::UnknownResult))
+            }
+        }
+    }
+
+    Err(SymbolicError {
+        ip,
+        kind: SymbolicErrorKind::StepLimit,
+    })
+}
+
+fn read(memory: &HashMap<usize, Option<Expr>>, address: usize) -> &Option<Expr> {
+    static ZERO: Option<Expr> = Some(Expr {
+        terms: BTreeMap::new(),
+    });
+    memory.get(&address).unwrap_or(&ZERO)
+}
+
+fn write(memory: &mut HashMap<usize, Option<Expr>>, address: usize, val: Option<Expr>) {
+    memory.insert(address, val);
+}
+
+/// Find values for the variables of `expr`, each within its range, for which
+/// it evaluates to `target`. Values are tried in increasing order, the last
+/// variable is solved for directly when the expression is linear in it and
+/// cannot wrap around over its range.
+pub fn solve(expr: &Expr, ranges: &[RangeInclusive<i64>], target: i64) -> Option<Vec<i64>> {
+    let mut values = vec![];
+    solve_from(expr, ranges, target, &mut values).then_some(values)
+}
+
+fn solve_from(
+    expr: &Expr,
+    ranges: &[RangeInclusive<i64>],
+    target: i64,
+    values: &mut Vec<i64>,
+) -> bool {
+    let var = values.len();
+    if var == ranges.len() {
+        return expr.eval(values) == target;
+    }
+
+    if var == ranges.len() - 1 {
+        if let Some(solution) = solve_linear(expr, var, &ranges[var], target, values) {
+            return match solution {
+                Some(x) if ranges[var].contains(&x) => {
+                    values.push(x);
+                    true
+                }
+                _ => false,
+            };
+        }
+    }
+
+    for x in ranges[var].clone() {
+        values.push(x);
+        if solve_from(expr, ranges, target, values) {
+            return true;
+        }
+        values.pop();
+    }
+    false
+}
+
+/// Solve `expr` for `var` directly when it is linear in it. Returns `None`
+/// when that cannot be done, either because it is not linear or because the
+/// product can wrap around over `range`, and the range must be scanned.
+fn solve_linear(
+    expr: &Expr,
+    var: usize,
+    range: &RangeInclusive<i64>,
+    target: i64,
+    values: &[i64],
+) -> Option<Option<i64>> {
+    let (a, b) = expr.linear_in(var, values)?;
+    // a*x is monotonic, so it cannot wrap if it does not at either end
+    a.checked_mul(*range.start())?;
+    a.checked_mul(*range.end())?;
+
+    let rest = target.wrapping_sub(b);
+    match a {
+        0 if rest == 0 => Some(Some(*range.start())),
+        0 => Some(None),
+        a => match rest.checked_rem(a)? {
+            0 => rest.checked_div(a).map(Some),
+            _ => Some(None),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_execute() {
+        // [0] = [1] * 3 + [2], after an instruction reading at [1] and [2]
+        let program = vec![1, 0, 0, 3, 1002, 1, 3, 0, 1, 0, 2, 0, 99];
+        let expr = execute(&program, &[1, 2], 0).unwrap();
+        assert_eq!(expr.to_string(), "3*x0 + x1");
+        assert_eq!(expr.eval(&[4, 5]), 17);
+        assert_eq!(solve(&expr, &[0..=99, 0..=99], 17), Some(vec![0, 17]));
+        assert_eq!(solve(&expr, &[1..=99, 0..=2], 17), Some(vec![5, 2]));
+        assert_eq!(solve(&expr, &[0..=3, 0..=2], 17), None);
+        assert_eq!(
+            execute(&program, &[1, 2], 3),
+            Err(SymbolicError {
+                ip: 12,
+                kind: SymbolicErrorKind::UnknownResult
+            })
+        );
+
+        // jumps to 8 if [4] is not 0
+        let program = vec![1005, 4, 8, 99, 0];
+        assert_eq!(
+            execute(&program, &[4], 0),
+            Err(SymbolicError {
+                ip: 0,
+                kind: SymbolicErrorKind::SymbolicCondition
+            })
+        );
+
+        // [5] = [5] * [5]
+        let program = vec![2, 5, 5, 5, 99, 0];
+        let expr = execute(&program, &[5], 5).unwrap();
+        assert_eq!(expr.to_string(), "x0*x0");
+        assert_eq!(solve(&expr, &[-10..=10], 49), Some(vec![-7]));
+
+        // [0] = [5] * -1, with a target which cannot be divided
+        let program = vec![1002, 5, -1, 0, 99, 0];
+        let expr = execute(&program, &[5], 0).unwrap();
+        assert_eq!(solve(&expr, &[-1..=1], i64::MIN), None);
+
+        // [0] = [5] * 2^62, which only reaches the target by wrapping around
+        let program = vec![1002, 5, 1 << 62, 0, 99, 0];
+        let expr = execute(&program, &[5], 0).unwrap();
+        assert_eq!(solve(&expr, &[0..=8], i64::MIN), Some(vec![2]));
+
+        // writes far away, then overflows the relative base
+        let program = vec![1101, 1, 1, 1_000_000_000_000, 109, i64::MAX, 109, 1, 99];
+        assert_eq!(
+            execute(&program, &[], 0),
+            Err(SymbolicError {
+                ip: 6,
+                kind: SymbolicErrorKind::Fault(FaultKind::WordOutOfRange)
+            })
+        );
+    }
+}
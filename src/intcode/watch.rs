@@ -0,0 +1,258 @@
+use super::Status;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Debug, Display, Formatter};
+use std::sync::Arc;
+
+type Callback<W> = dyn Fn(&Hit<W>) + Send + Sync;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+impl Display for Access {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Access::Read => write!(f, "read"),
+            Access::Write => write!(f, "write"),
+        }
+    }
+}
+
+/// Access to a watched memory cell.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Hit<W = i64> {
+    /// Address of the instruction making the access.
+    pub ip: usize,
+    pub address: usize,
+    pub access: Access,
+    /// Value read, or written.
+    pub value: W,
+}
+
+impl<W: Display> Display for Hit<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} at [{}] by instruction {}",
+            self.access, self.value, self.address, self.ip
+        )
+    }
+}
+
+/// What to do when a watched cell is accessed.
+#[derive(Clone)]
+pub enum Action<W = i64> {
+    /// Stop with `Status::Watchpoint` once the instruction has executed.
+    Pause,
+    /// Call a function, and keep running.
+    Call(Arc<Callback<W>>),
+}
+
+impl<W> Debug for Action<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Pause => write!(f, "Pause"),
+            Action::Call(_) => write!(f, "Call"),
+        }
+    }
+}
+
+/// Write of a cell which was previously executed as part of an instruction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SelfModification<W = i64> {
+    /// Address of the writing instruction.
+    pub ip: usize,
+    pub address: usize,
+    pub value: W,
+}
+
+impl<W: Display> Display for SelfModification<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "instruction {} wrote {} into code at [{}]",
+            self.ip, self.value, self.address
+        )
+    }
+}
+
+/// Watchpoints and self-modification detection of an `IntCodeComputer`.
+#[derive(Clone, Debug, Default)]
+pub struct Watches<W = i64> {
+    points: HashMap<(usize, Access), Action<W>>,
+    /// Cells executed so far, only tracked when detecting self-modification.
+    executed: Option<HashSet<usize>>,
+    pub modifications: Vec<SelfModification<W>>,
+    /// First hit asking to pause during the current instruction.
+    pub paused: Option<Hit<W>>,
+}
+
+impl<W: Clone> Watches<W> {
+    pub fn watch(&mut self, address: usize, access: Access, action: Action<W>) {
+        self.points.insert((address, access), action);
+    }
+
+    pub fn unwatch(&mut self, address: usize, access: Access) {
+        self.points.remove(&(address, access));
+    }
+
+    pub fn detect_self_modification(&mut self, enabled: bool) {
+        self.executed = if enabled { Some(HashSet::new()) } else { None };
+    }
+
+    /// Record the cells of an instruction about to be executed.
+    pub fn execute(&mut self, ip: usize, size: usize) {
+        if let (Some(executed), Some(end)) = (&mut self.executed, ip.checked_add(size)) {
+            executed.extend(ip..end);
+        }
+    }
+
+    pub fn read(&mut self, ip: usize, address: usize, value: &W) {
+        self.hit(ip, address, Access::Read, value);
+    }
+
+    pub fn write(&mut self, ip: usize, address: usize, value: &W) {
+        let executed = self.executed.as_ref();
+        if executed.is_some_and(|executed| executed.contains(&address)) {
+            self.modifications.push(SelfModification {
+                ip,
+                address,
+                value: value.clone(),
+            });
+        }
+        self.hit(ip, address, Access::Write, value);
+    }
+
+    /// Turn the status of an instruction which asked to pause into a
+    /// `Status::Watchpoint`. A pause during an instruction which outputs is
+    /// reported by the next step instead, as is the halt of an instruction
+    /// which paused. A pause during an instruction which did not complete is
+    /// dropped, running it again will pause again.
+    #[cold]
+    pub fn pause<E>(&mut self, status: Result<Status<W>, E>) -> Result<Status<W>, E> {
+        match status {
+            Ok(Status::Running) => Ok(self
+                .paused
+                .take()
+                .map_or(Status::Running, Status::Watchpoint)),
+            Ok(Status::Halted) => Ok(self
+                .paused
+                .take()
+                .map_or(Status::Halted, Status::Watchpoint)),
+            Ok(Status::Output(val)) => Ok(Status::Output(val)),
+            status => {
+                self.paused = None;
+                status
+            }
+        }
+    }
+
+    fn hit(&mut self, ip: usize, address: usize, access: Access, value: &W) {
+        let action = match self.points.get(&(address, access)) {
+            Some(action) => action,
+            None => return,
+        };
+        let hit = Hit {
+            ip,
+            address,
+            access,
+            value: value.clone(),
+        };
+        match action {
+            Action::Pause => {
+                self.paused.get_or_insert(hit);
+            }
+            Action::Call(f) => f(&hit),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{IntCodeComputer, NoIO, Status, IO};
+    use std::io;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_watchpoints() {
+        // [9] <- [9] + #1, out [9], halt
+        let program = vec![1001, 9, 1, 9, 4, 9, 99, 0, 0, 41];
+        let mut computer = IntCodeComputer::new(program, NoIO {});
+        let reads = Arc::new(Mutex::new(vec![]));
+        let log = reads.clone();
+        computer.watch(
+            9,
+            Access::Read,
+            Action::Call(Arc::new(move |hit: &Hit| log.lock().unwrap().push(hit.ip))),
+        );
+        computer.watch(9, Access::Write, Action::Pause);
+
+        let hit = Hit {
+            ip: 0,
+            address: 9,
+            access: Access::Write,
+            value: 42,
+        };
+        assert_eq!(hit.to_string(), "write of 42 at [9] by instruction 0");
+        assert_eq!(computer.resume(), Ok(Status::Watchpoint(hit)));
+        assert_eq!(computer.resume(), Ok(Status::Output(42)));
+        assert_eq!(computer.resume(), Ok(Status::Halted));
+        assert_eq!(*reads.lock().unwrap(), vec![0, 4]);
+    }
+
+    #[test]
+    fn test_pause_before_halt() {
+        struct Closed;
+        impl IO for Closed {
+            fn get(&mut self) -> io::Result<i64> {
+                Err(io::Error::from(io::ErrorKind::BrokenPipe))
+            }
+
+            fn put(&mut self, _: i64) -> io::Result<()> {
+                Err(io::Error::from(io::ErrorKind::BrokenPipe))
+            }
+        }
+
+        // out [3], which halts as nobody reads the output
+        let program = vec![4, 3, 99, 42];
+        let mut computer = IntCodeComputer::new(program, Closed);
+        computer.watch(3, Access::Read, Action::Pause);
+
+        let hit = Hit {
+            ip: 0,
+            address: 3,
+            access: Access::Read,
+            value: 42,
+        };
+        assert_eq!(computer.step(), Ok(Status::Watchpoint(hit)));
+        assert_eq!(computer.step(), Ok(Status::Halted));
+        assert_eq!(computer.step(), Ok(Status::Halted));
+    }
+
+    #[test]
+    fn test_self_modification() {
+        // out #1, then overwrite it with halt and jump back to it
+        let program = vec![104, 1, 1101, 99, 0, 0, 1105, 1, 0];
+        let mut computer = IntCodeComputer::new(program, NoIO {});
+        computer.detect_self_modification(true);
+        assert_eq!(computer.run(), Ok(Status::Halted));
+        assert_eq!(
+            computer.self_modifications(),
+            &[SelfModification {
+                ip: 2,
+                address: 0,
+                value: 99,
+            }]
+        );
+
+        // write a halt far away and jump to it
+        let program = vec![1101, 99, 0, 1 << 40, 1106, 0, 1 << 40];
+        let mut computer = IntCodeComputer::new(program, NoIO {});
+        computer.detect_self_modification(true);
+        assert_eq!(computer.run(), Ok(Status::Halted));
+        assert_eq!(computer.self_modifications(), &[]);
+    }
+}
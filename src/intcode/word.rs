@@ -0,0 +1,52 @@
+use num::{BigInt, CheckedAdd, CheckedMul, One, ToPrimitive, Zero};
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+
+/// Integer type an `IntCodeComputer` stores in memory and computes with.
+pub trait Word:
+    Clone
+    + Debug
+    + Default
+    + Display
+    + FromStr
+    + Ord
+    + From<i64>
+    + Zero
+    + One
+    + ToPrimitive
+    + CheckedAdd
+    + CheckedMul
+{
+    /// Sum that wraps around on overflow for fixed-size integers.
+    fn wrapping_add(&self, other: &Self) -> Self;
+    /// Product that wraps around on overflow for fixed-size integers.
+    fn wrapping_mul(&self, other: &Self) -> Self;
+}
+
+macro_rules! impl_word {
+    ($($t:ty),*) => {
+        $(
+            impl Word for $t {
+                fn wrapping_add(&self, other: &Self) -> Self {
+                    <$t>::wrapping_add(*self, *other)
+                }
+
+                fn wrapping_mul(&self, other: &Self) -> Self {
+                    <$t>::wrapping_mul(*self, *other)
+                }
+            }
+        )*
+    };
+}
+
+impl_word!(i64, i128);
+
+impl Word for BigInt {
+    fn wrapping_add(&self, other: &Self) -> Self {
+        self + other
+    }
+
+    fn wrapping_mul(&self, other: &Self) -> Self {
+        self * other
+    }
+}
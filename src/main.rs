@@ -1,16 +1,256 @@
+use crate::intcode::{
+    asm::assemble,
+    cfg::Cfg,
+    console::{ConsoleIO, Mode},
+    debugger::Debugger,
+    disasm::disassemble,
+    fuzz::{self, FailureKind},
+    parse_program,
+    profiler::Profiler,
+    record::{RecordIO, ReplayIO},
+    IntCodeComputer, NoIO, Status, IO,
+};
 use crate::solutions::exec_day;
-use std::env;
+use crate::solver::input_file;
+use itertools::Itertools;
+use std::{
+    env,
+    fmt::Display,
+    fs,
+    fs::File,
+    io, process,
+    time::{Duration, Instant},
+};
+
+use adv_rs_2019::intcode;
 
 mod grid;
-mod intcode;
 mod solutions;
 mod solver;
 
 fn main() {
-    let day = env::args()
-        .nth(1)
-        .unwrap_or_else(|| String::from("1"))
-        .parse()
-        .unwrap_or(1);
-    exec_day(day);
+    let mut args = env::args().skip(1);
+    match args.next().as_deref() {
+        Some("asm") => asm(&args.next().expect("missing source file")),
+        Some("bench") => {
+            let program = match args.next() {
+                Some(arg) => load_program(&program_file(Some(arg))),
+                None => assemble(BENCH_WORKLOAD).expect("invalid benchmark workload"),
+            };
+            bench(program, &args.flat_map(|a| a.parse()).collect::<Vec<_>>())
+        }
+        Some("cfg") => cfg(&program_file(args.next())),
+        Some("debug") => debug(&program_file(args.next())),
+        Some("disasm") => disasm(&program_file(args.next())),
+        Some("fuzz") => fuzz(
+            args.next()
+                .map_or(10_000, |a| a.parse().expect("invalid iterations")),
+            args.next().map_or(0, |a| a.parse().expect("invalid seed")),
+        ),
+        Some("profile") => profile(
+            &program_file(args.next()),
+            &args.flat_map(|a| a.parse()).collect::<Vec<_>>(),
+        ),
+        Some("run") => {
+            let path = program_file(args.next());
+            let mut mode = Mode::Numbers;
+            let mut recording = None;
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--ascii" => mode = Mode::Ascii,
+                    "--record" => recording = Some(args.next().expect("missing recording file")),
+                    _ => panic!("unknown option {}", arg),
+                }
+            }
+            run(&path, mode, recording.as_deref())
+        }
+        Some("replay") => replay(
+            &program_file(args.next()),
+            &args.next().expect("missing recording file"),
+        ),
+        day => exec_day(day.unwrap_or("1").parse().unwrap_or(1)),
+    }
+}
+
+/// Report the error which made a command fail, and exit with a non-zero status.
+fn fail(e: impl Display) -> ! {
+    eprintln!("Error: {}", e);
+    process::exit(1)
+}
+
+/// Program file given either as a path or as the day whose input to use.
+fn program_file(arg: Option<String>) -> String {
+    let arg = arg.unwrap_or_else(|| String::from("1"));
+    arg.parse().map(input_file).unwrap_or(arg)
+}
+
+fn disasm(path: &str) {
+    let f = File::open(path).expect("unable to open program file");
+    for line in disassemble(&parse_program(f)) {
+        println!("{}", line);
+    }
+}
+
+fn cfg(path: &str) {
+    let f = File::open(path).expect("unable to open program file");
+    Cfg::build(&parse_program(f))
+        .write_dot(io::stdout())
+        .expect("unable to write graph");
+}
+
+fn asm(path: &str) {
+    let source = fs::read_to_string(path).expect("unable to open source file");
+    match assemble(&source) {
+        Ok(program) => println!("{}", program.iter().join(",")),
+        Err(e) => fail(e),
+    }
+}
+
+/// Program run by `bench` when none is given: nested loops executing about
+/// 600k instructions, to compare the speed of the computer across changes.
+const BENCH_WORKLOAD: &str = "
+            add  #0, #0, [i]
+    outer:  add  #0, #0, [j]
+    inner:  add  [j], #1, [j]
+            lt   [j], #200, [t]
+            jit  [t], #inner
+            add  [i], #1, [i]
+            lt   [i], #1000, [t]
+            jit  [t], #outer
+            out  [i]
+            halt
+    i:      data 0
+    j:      data 0
+    t:      data 0
+";
+
+fn load_program(path: &str) -> Vec<i64> {
+    let f = File::open(path).expect("unable to open program file");
+    parse_program(f)
+}
+
+/// Run a program repeatedly for a few seconds and report its throughput.
+fn bench(program: Vec<i64>, inputs: &[i64]) {
+    let start = Instant::now();
+    let mut runs = 0;
+    while start.elapsed() < Duration::from_secs(3) {
+        let mut computer = IntCodeComputer::new(program.clone(), NoIO {});
+        for &input in inputs {
+            computer.push_input(input);
+        }
+        if let Err(e) = computer.run() {
+            fail(e);
+        }
+        runs += 1;
+    }
+
+    let elapsed = start.elapsed();
+    println!(
+        "{} runs in {:.2?}, {:.2?} per run",
+        runs,
+        elapsed,
+        elapsed / runs
+    );
+}
+
+/// Run generated programs on the Intcode computer and on a reference
+/// interpreter, and report the first one on which they disagree.
+fn fuzz(iterations: u64, seed: u64) {
+    match fuzz::fuzz(seed, iterations, fuzz::computer, Some(fuzz::reference)) {
+        Ok(()) => println!("{} programs run without any difference", iterations),
+        Err(failure) => {
+            println!("Seed {} failed", failure.seed);
+            println!("Program: {}", failure.program.iter().join(","));
+            println!("Inputs: {}", failure.inputs.iter().join(","));
+            match failure.kind {
+                FailureKind::Panic => println!("The computer panicked"),
+                FailureKind::Mismatch { expected, found } => {
+                    println!("Expected: {:?}", expected);
+                    println!("Found: {:?}", found);
+                }
+            }
+            process::exit(1);
+        }
+    }
+}
+
+fn debug(path: &str) {
+    let f = File::open(path).expect("unable to open program file");
+    let computer = IntCodeComputer::new(parse_program(f), NoIO {});
+    let stdin = io::stdin();
+    Debugger::new(computer)
+        .run(stdin.lock(), io::stdout())
+        .unwrap_or_else(|e| fail(e));
+}
+
+fn profile(path: &str, inputs: &[i64]) {
+    let f = File::open(path).expect("unable to open program file");
+    let program = parse_program(f);
+    let mut computer =
+        IntCodeComputer::new(program.clone(), NoIO {}).with_tracer(Profiler::default());
+    computer.detect_self_modification(true);
+    for &input in inputs {
+        computer.push_input(input);
+    }
+
+    // the report is still printed for a program which faulted
+    let error = loop {
+        match computer.resume() {
+            Ok(Status::Output(value)) => println!("Output: {}", value),
+            Ok(Status::WaitingForInput) => {
+                println!("Program is waiting for more input");
+                break None;
+            }
+            Ok(_) => break None,
+            Err(e) => break Some(e),
+        }
+    };
+
+    println!();
+    print!("{}", computer.tracer.report(&program));
+
+    println!("\nSelf-modifying writes:");
+    for modification in computer.self_modifications() {
+        println!("{:>12}  {}", "", modification);
+    }
+    if let Some(e) = error {
+        fail(e);
+    }
+}
+
+/// Run a program against a recorded session and check it produces the same outputs.
+fn replay(path: &str, recording: &str) {
+    let f = File::open(path).expect("unable to open program file");
+    let recording = File::open(recording).expect("unable to open recording file");
+    let io = ReplayIO::load(io::BufReader::new(recording)).expect("invalid recording");
+    let mut computer = IntCodeComputer::new(parse_program(f), io);
+    // a mismatch makes the program fail, report it rather than the fault
+    let result = computer.run();
+    match (computer.io.finish(), result) {
+        (Ok(()), Ok(_)) => println!("Replay matches the recording"),
+        (Ok(()), Err(e)) => fail(e),
+        (Err(e), _) => fail(format!("replay differs: {}", e)),
+    }
+}
+
+/// Run a program interactively, reading its input from stdin and printing its output.
+fn run(path: &str, mode: Mode, recording: Option<&str>) {
+    let program = load_program(path);
+    let console = ConsoleIO::new(io::stdin().lock(), io::stdout(), mode);
+    match recording {
+        Some(recording) => {
+            let log = File::create(recording).expect("unable to create recording file");
+            run_interactive(program, RecordIO::new(console, log));
+        }
+        None => run_interactive(program, console),
+    }
+}
+
+fn run_interactive<T: IO>(program: Vec<i64>, io: T) {
+    let mut computer = IntCodeComputer::new(program, io);
+    match computer.run() {
+        Ok(Status::WaitingForInput) => println!("Program is waiting for more input"),
+        Ok(_) => println!("Program halted"),
+        Err(e) => fail(e),
+    }
 }
@@ -1,7 +1,14 @@
-use crate::intcode::{parse_program, IntCodeComputer, NoIO};
+use crate::intcode::{
+    parse_program,
+    symbolic::{self, solve},
+    IntCodeComputer, NoIO,
+};
 use crate::solver::Solver;
 use std::io::Read;
 
+/// Output the noun and verb must produce in part 2.
+const TARGET: i64 = 19690720;
+
 pub struct Problem;
 
 impl Solver for Problem {
@@ -18,25 +25,42 @@ impl Solver for Problem {
         program[1] = 12;
         program[2] = 2;
         let mut computer = IntCodeComputer::new(program, NoIO {});
-        computer.run();
-        computer.program[0]
+        if let Err(e) = computer.run() {
+            panic!("gravity assist program failed: {}", e);
+        }
+        computer.memory[0]
     }
 
     fn solve_second(&self, input: &Vec<i64>) -> i64 {
-        for noun in 0..=99 {
-            for verb in 0..=99 {
-                let mut program = input.clone();
-                program[1] = noun;
-                program[2] = verb;
-                let mut computer = IntCodeComputer::new(program, NoIO {});
-                computer.run();
-                let output = computer.program[0];
-
-                if output == 19690720 {
-                    return 100 * noun + verb;
-                }
+        // the output is usually a simple expression of the noun and verb,
+        // only run every pair when it cannot be worked out
+        let solution = match symbolic::execute(input, &[1, 2], 0) {
+            Ok(expr) => solve(&expr, &[0..=99, 0..=99], TARGET)
+                .map(|values| 100 * values[0] + values[1])
+                .or_else(|| brute_force(input)),
+            Err(_) => brute_force(input),
+        };
+        solution.expect("no noun and verb produce the target")
+    }
+}
+
+fn brute_force(input: &[i64]) -> Option<i64> {
+    for noun in 0..=99 {
+        for verb in 0..=99 {
+            let mut program = input.to_vec();
+            program[1] = noun;
+            program[2] = verb;
+            let mut computer = IntCodeComputer::new(program, NoIO {});
+            if computer.run().is_err() {
+                // this noun/verb pair crashes the program, try the next one
+                continue;
+            }
+            let output = computer.memory[0];
+
+            if output == TARGET {
+                return Some(100 * noun + verb);
             }
         }
-        0
     }
+    None
 }
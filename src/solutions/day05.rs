@@ -1,8 +1,8 @@
 use crate::{
-    intcode::{parse_program, IntCodeComputer, IO},
+    intcode::{parse_program, IntCodeComputer, QueueIO},
     solver::Solver,
 };
-use std::io::{self, Read};
+use std::io::Read;
 
 pub struct Problem;
 
@@ -16,29 +16,22 @@ impl Solver for Problem {
     }
 
     fn solve_first(&self, input: &Self::Input) -> Self::Output1 {
-        let mut computer = IntCodeComputer::new(input.clone(), SimpleIO { val: 1 });
-        computer.run();
-        computer.io.val
+        diagnostic_code(input, 1)
     }
 
     fn solve_second(&self, input: &Self::Input) -> Self::Output2 {
-        let mut computer = IntCodeComputer::new(input.clone(), SimpleIO { val: 5 });
-        computer.run();
-        computer.io.val
+        diagnostic_code(input, 5)
     }
 }
 
-struct SimpleIO {
-    val: i64,
-}
-
-impl IO for SimpleIO {
-    fn get(&mut self) -> io::Result<i64> {
-        Ok(self.val)
-    }
-
-    fn put(&mut self, val: i64) -> io::Result<()> {
-        self.val = val;
-        Ok(())
+fn diagnostic_code(program: &[i64], system_id: i64) -> i64 {
+    let mut computer = IntCodeComputer::new(program.to_vec(), QueueIO::new(&[system_id]));
+    if let Err(e) = computer.run() {
+        panic!("diagnostic program failed: {}", e);
     }
+    computer
+        .io
+        .drain_outputs()
+        .pop()
+        .expect("diagnostic program output nothing")
 }
@@ -1,9 +1,9 @@
 use crate::{
-    intcode::{self, parse_program, AsyncIO, Connector, IntCodeComputer},
+    intcode::{network::Network, parse_program, Budget},
     solver::Solver,
 };
 use itertools::Itertools;
-use std::{io::Read, iter::from_fn, sync::mpsc::channel, thread::spawn};
+use std::io::Read;
 
 pub struct Problem;
 
@@ -19,7 +19,7 @@ impl Solver for Problem {
     fn solve_first(&self, input: &Self::Input) -> Self::Output1 {
         (0..5)
             .permutations(5)
-            .map(|phases| run_with_phases(input, &phases))
+            .map(|phases| run_amplifiers(input, &phases, false))
             .max()
             .unwrap()
     }
@@ -27,79 +27,61 @@ impl Solver for Problem {
     fn solve_second(&self, input: &Self::Input) -> Self::Output2 {
         (5..10)
             .permutations(5)
-            .map(|phases| run_with_phases_async(input, &phases))
+            .map(|phases| run_amplifiers(input, &phases, true))
             .max()
             .unwrap()
     }
 }
 
-fn run_with_phases(program: &[i64], phases: &[i64]) -> i64 {
-    let mut input = 0;
-    for &phase in phases {
-        let (io, tx, rx) = AsyncIO::new();
-        let _ = tx.send(phase);
-        let _ = tx.send(input);
-
-        let mut computer = IntCodeComputer::new(program.to_owned(), io);
-        computer.run();
-        drop(computer);
+/// Instructions an amplifier may execute before it is considered stuck.
+const AMPLIFIER_BUDGET: u64 = 10_000_000;
+
+/// Run amplifiers in series, each with its phase, and return the last output
+/// of the last one. With `feedback`, its outputs are also sent back to the first.
+fn run_amplifiers(program: &[i64], phases: &[i64], feedback: bool) -> i64 {
+    let mut network = Network::new();
+    let amplifiers = phases
+        .iter()
+        .map(|&phase| {
+            let amplifier = network.add_machine(program.to_vec());
+            network.seed(amplifier, &[phase]);
+            amplifier
+        })
+        .collect::<Vec<_>>();
+    for (&from, &to) in amplifiers.iter().tuple_windows() {
+        network.connect(from, to);
+    }
 
-        input = from_fn(|| rx.recv().ok()).last().unwrap();
+    let (first, last) = (amplifiers[0], amplifiers[amplifiers.len() - 1]);
+    if feedback {
+        network.connect(last, first);
+    }
+    network
+        .seed(first, &[0])
+        .tap(last)
+        .budget(Budget::instructions(AMPLIFIER_BUDGET));
+
+    match network.run() {
+        Ok(outputs) => *outputs.last().expect("amplifiers output nothing"),
+        Err(e) => panic!("amplifiers failed: {}", e),
     }
-    input
 }
 
-fn run_with_phases_async(program: &[i64], phases: &[i64]) -> i64 {
-    // setup io
-    let (a_io, a_tx, a_rx) = intcode::AsyncIO::new();
-    let (b_io, b_tx, b_rx) = intcode::AsyncIO::new();
-    let (c_io, c_tx, c_rx) = intcode::AsyncIO::new();
-    let (d_io, d_tx, d_rx) = intcode::AsyncIO::new();
-    let (e_io, e_tx, e_rx) = intcode::AsyncIO::new();
-    let (o_tx, o_rx) = channel();
-
-    let _ = a_tx.send(phases[0]);
-    let _ = a_tx.send(0);
-    let _ = b_tx.send(phases[1]);
-    let _ = c_tx.send(phases[2]);
-    let _ = d_tx.send(phases[3]);
-    let _ = e_tx.send(phases[4]);
-
-    let ab_cnx = Connector::new(b_tx, a_rx);
-    let bc_cnx = Connector::new(c_tx, b_rx);
-    let cd_cnx = Connector::new(d_tx, c_rx);
-    let de_cnx = Connector::new(e_tx, d_rx);
-    let eao_cnx = Connector::multiplexed(vec![a_tx, o_tx], e_rx);
-
-    // setup computers
-    let mut a_computer = IntCodeComputer::new(program.to_vec(), a_io);
-    let mut b_computer = IntCodeComputer::new(program.to_vec(), b_io);
-    let mut c_computer = IntCodeComputer::new(program.to_vec(), c_io);
-    let mut d_computer = IntCodeComputer::new(program.to_vec(), d_io);
-    let mut e_computer = IntCodeComputer::new(program.to_vec(), e_io);
-
-    // receive thread
-    let output_thread = spawn(move || from_fn(|| o_rx.recv().ok()).last().unwrap());
-
-    // run all in threads
-    let threads = vec![
-        spawn(move || a_computer.run()),
-        spawn(move || b_computer.run()),
-        spawn(move || c_computer.run()),
-        spawn(move || d_computer.run()),
-        spawn(move || e_computer.run()),
-        spawn(move || ab_cnx.run()),
-        spawn(move || bc_cnx.run()),
-        spawn(move || cd_cnx.run()),
-        spawn(move || de_cnx.run()),
-        spawn(move || eao_cnx.run()),
-    ];
-
-    // wait
-    for t in threads {
-        let _ = t.join();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_amplifiers() {
+        let program = parse_program(&b"3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0"[..]);
+        assert_eq!(run_amplifiers(&program, &[4, 3, 2, 1, 0], false), 43210);
+
+        let program = parse_program(
+            &b"3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5"[..],
+        );
+        assert_eq!(
+            run_amplifiers(&program, &[9, 8, 7, 6, 5], true),
+            139_629_729
+        );
     }
-
-    // wait for final output value
-    output_thread.join().unwrap()
 }
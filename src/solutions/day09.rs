@@ -1,6 +1,5 @@
-use crate::intcode::AsyncIO;
 use crate::{
-    intcode::{parse_program, IntCodeComputer},
+    intcode::{parse_program, IntCodeComputer, QueueIO},
     solver::Solver,
 };
 use std::io::Read;
@@ -17,40 +16,40 @@ impl Solver for Problem {
     }
 
     fn solve_first(&self, input: &Self::Input) -> Self::Output1 {
-        let (io, tx, rx) = AsyncIO::new();
-        let _ = tx.send(1);
+        let mut computer = IntCodeComputer::new(input.to_vec(), QueueIO::new(&[1]));
+        if let Err(e) = computer.run() {
+            panic!("BOOST program failed: {}", e);
+        }
 
-        let mut computer = IntCodeComputer::new(input.to_vec(), io);
-        computer.run();
-
-        rx.recv().unwrap()
+        computer
+            .io
+            .output
+            .pop_front()
+            .expect("BOOST program output nothing")
     }
 
     fn solve_second(&self, input: &Self::Input) -> Self::Output2 {
-        let (io, tx, rx) = AsyncIO::new();
-        let _ = tx.send(2);
-
-        let mut computer = IntCodeComputer::new(input.to_vec(), io);
-        computer.run();
+        let mut computer = IntCodeComputer::new(input.to_vec(), QueueIO::new(&[2]));
+        if let Err(e) = computer.run() {
+            panic!("BOOST program failed: {}", e);
+        }
 
-        rx.recv().unwrap()
+        computer
+            .io
+            .output
+            .pop_front()
+            .expect("BOOST program output nothing")
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::iter::from_fn;
 
     fn assert_output_eq(program: &[i64], expected_output: &[i64]) {
-        let (io, tx, rx) = AsyncIO::new();
-        let _ = tx.send(1);
-        let mut computer = IntCodeComputer::new(program.to_vec(), io);
-        computer.run();
-        assert_eq!(
-            from_fn(|| rx.recv().ok()).collect::<Vec<_>>(),
-            expected_output.to_vec(),
-        );
+        let mut computer = IntCodeComputer::new(program.to_vec(), QueueIO::new(&[1]));
+        computer.run().unwrap();
+        assert_eq!(computer.io.drain_outputs(), expected_output.to_vec());
     }
 
     #[test]
@@ -183,7 +183,11 @@ impl Robot {
         let (io, tx, rx) = AsyncIO::new();
         let mut computer = IntCodeComputer::new(program, io);
 
-        let handle = thread::spawn(move || computer.run());
+        let handle = thread::spawn(move || {
+            if let Err(e) = computer.run() {
+                panic!("painting robot program failed: {}", e);
+            }
+        });
 
         Self {
             position: Point { x: 0, y: 0 },
@@ -195,7 +199,7 @@ impl Robot {
     }
 
     fn wait(self) {
-        let _ = self.handle.join();
+        self.handle.join().expect("painting robot thread failed");
     }
 
     fn paint(&mut self, panel: &mut Panel) -> Result<(), Box<dyn Error>> {
@@ -1,11 +1,8 @@
-use crate::intcode::AsyncIO;
 use crate::{
-    intcode::{parse_program, IntCodeComputer},
+    intcode::{parse_program, IntCodeComputer, NoIO, Status, IO},
     solver::Solver,
 };
-use std::{
-    cmp::Ordering, collections::HashMap, io::Read, iter::repeat, sync::mpsc::Receiver, thread,
-};
+use std::{cmp::Ordering, collections::HashMap, io::Read, iter::repeat};
 
 pub struct Problem;
 
@@ -19,24 +16,20 @@ impl Solver for Problem {
     }
 
     fn solve_first(&self, input: &Self::Input) -> Self::Output1 {
-        let (io, _tx, rx) = AsyncIO::new();
-        let mut computer = IntCodeComputer::new(input.clone(), io);
-
-        let handle = thread::spawn(move || computer.run());
+        let mut computer = IntCodeComputer::new(input.clone(), NoIO {});
 
         let mut screen = Screen {
             score: 0,
             cells: Default::default(),
         };
 
-        while let Some((x, y, tile_id)) = recv_all(&rx) {
+        while let Ok((x, y, tile_id)) = next_tile(&mut computer) {
             if x == -1 {
                 screen.score = tile_id;
             }
             screen.cells.insert(Pos { x, y }, Tile::from_i64(tile_id));
         }
 
-        let _ = handle.join();
         screen
             .cells
             .values()
@@ -45,11 +38,9 @@ impl Solver for Problem {
     }
 
     fn solve_second(&self, input: &Self::Input) -> Self::Output2 {
-        let (io, tx, rx) = AsyncIO::new();
         let mut program = input.clone();
         program[0] = 2; // play for free haxxxx
-        let mut computer = IntCodeComputer::new(program, io);
-        let handle = thread::spawn(move || computer.run());
+        let mut computer = IntCodeComputer::new(program, NoIO {});
         let mut screen = Screen {
             score: 0,
             cells: Default::default(),
@@ -57,9 +48,22 @@ impl Solver for Problem {
 
         let mut ball_x = 0;
         let mut paddle_x = 0;
-        let mut started = false;
 
-        while let Some((x, y, tile_id)) = recv_all(&rx) {
+        loop {
+            let (x, y, tile_id) = match next_tile(&mut computer) {
+                Ok(tile) => tile,
+                Err(Status::WaitingForInput) => {
+                    // game waits for the joystick, move paddle towards ball
+                    computer.push_input(match paddle_x.cmp(&ball_x) {
+                        Ordering::Less => 1,
+                        Ordering::Equal => 0,
+                        Ordering::Greater => -1,
+                    });
+                    continue;
+                }
+                Err(_) => break,
+            };
+
             if x == -1 && y == 0 {
                 screen.score = tile_id;
                 continue;
@@ -73,37 +77,32 @@ impl Solver for Problem {
                 Tile::Paddle => paddle_x = x,
                 _ => {}
             }
-            screen.cells.insert(Pos { x, y }, Tile::from_i64(tile_id));
-
-            if started {
-                // program waits for input as soon as it printed a ball
-                if tile == Tile::Ball {
-                    // move paddle towards ball
-                    tx.send(match paddle_x.cmp(&ball_x) {
-                        Ordering::Less => 1,
-                        Ordering::Equal => 0,
-                        Ordering::Greater => -1,
-                    })
-                    .unwrap_or_else(|e| println!("Error: {}", e));
-                }
+            screen.cells.insert(Pos { x, y }, tile);
 
             //screen.display();
             //thread::sleep(Duration::from_millis(10));
-            } else if x == 39 && y == 19 {
-                // effectively start game when all the field is drawn
-                started = true;
-                let _ = tx.send(0);
-            }
         }
 
-        let _ = handle.join();
-
         screen.score
     }
 }
 
-fn recv_all(rx: &Receiver<i64>) -> Option<(i64, i64, i64)> {
-    Some((rx.recv().ok()?, rx.recv().ok()?, rx.recv().ok()?))
+/// Run the program until it has drawn a tile, or return the status which
+/// interrupted it.
+fn next_tile<T: IO>(computer: &mut IntCodeComputer<T>) -> Result<(i64, i64, i64), Status> {
+    Ok((
+        next_value(computer)?,
+        next_value(computer)?,
+        next_value(computer)?,
+    ))
+}
+
+fn next_value<T: IO>(computer: &mut IntCodeComputer<T>) -> Result<i64, Status> {
+    match computer.resume() {
+        Ok(Status::Output(value)) => Ok(value),
+        Ok(status) => Err(status),
+        Err(e) => panic!("arcade program failed: {}", e),
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Hash)]
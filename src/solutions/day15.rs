@@ -1,5 +1,5 @@
 use crate::{
-    intcode::{parse_program, AsyncIO, IntCodeComputer},
+    intcode::{parse_program, IntCodeComputer, NoIO, Status, IO},
     solver::Solver,
 };
 use itertools::repeat_n;
@@ -8,8 +8,6 @@ use std::{
     convert::TryFrom,
     fmt::{Display, Error, Formatter},
     io::Read,
-    sync::mpsc::{Receiver, Sender},
-    thread::spawn,
 };
 
 pub struct Problem;
@@ -24,25 +22,15 @@ impl Solver for Problem {
     }
 
     fn solve_first(&self, input: &Self::Input) -> Self::Output1 {
-        let (io, tx, rx) = AsyncIO::new();
-        let mut computer = IntCodeComputer::new(input.to_vec(), io);
-
-        let t = spawn(move || computer.run());
-        let map = build_map(&tx, &rx);
-        drop(tx);
-        let _ = t.join();
+        let mut computer = IntCodeComputer::new(input.to_vec(), NoIO {});
+        let map = build_map(&mut computer);
 
         find_steps_from_origin(&map)
     }
 
     fn solve_second(&self, input: &Self::Input) -> Self::Output2 {
-        let (io, tx, rx) = AsyncIO::new();
-        let mut computer = IntCodeComputer::new(input.to_vec(), io);
-
-        let t = spawn(move || computer.run());
-        let map = build_map(&tx, &rx);
-        drop(tx);
-        let _ = t.join();
+        let mut computer = IntCodeComputer::new(input.to_vec(), NoIO {});
+        let map = build_map(&mut computer);
 
         oxygen_fill(&map)
     }
@@ -239,7 +227,7 @@ impl Display for Map {
     }
 }
 
-fn build_map(tx: &Sender<i64>, rx: &Receiver<i64>) -> Map {
+fn build_map<T: IO>(computer: &mut IntCodeComputer<T>) -> Map {
     let mut visited = HashSet::new();
     let mut points = HashMap::new();
     let mut cur_pos = Point { x: 0, y: 0 };
@@ -252,7 +240,7 @@ fn build_map(tx: &Sender<i64>, rx: &Receiver<i64>) -> Map {
         visited.insert((cur_pos.clone(), cur_dir.clone()));
 
         // try to advance in the same direction
-        let status = robot_turn(&cur_dir, tx, rx);
+        let status = robot_turn(&cur_dir, computer);
 
         match status {
             0 => {
@@ -279,9 +267,12 @@ fn build_map(tx: &Sender<i64>, rx: &Receiver<i64>) -> Map {
     Map::from_points_map(&points)
 }
 
-fn robot_turn(dir: &Dir, tx: &Sender<i64>, rx: &Receiver<i64>) -> i64 {
-    let _ = tx.send(dir.into());
-    rx.recv().unwrap()
+fn robot_turn<T: IO>(dir: &Dir, computer: &mut IntCodeComputer<T>) -> i64 {
+    computer.push_input(dir.into());
+    match computer.resume() {
+        Ok(Status::Output(status)) => status,
+        other => panic!("robot did not report its status: {:?}", other),
+    }
 }
 
 fn find_steps_from_origin(map: &Map) -> u64 {
@@ -1,6 +1,6 @@
 use crate::{
     grid::Grid,
-    intcode::{parse_program, AsyncIO, IntCodeComputer},
+    intcode::{ascii::AsciiIO, parse_program, IntCodeComputer},
     solver::Solver,
 };
 use itertools::Itertools;
@@ -8,11 +8,17 @@ use std::{
     convert::TryFrom,
     fmt::{Display, Error, Formatter},
     io::Read,
-    iter::from_fn,
+    iter,
     str::FromStr,
-    thread::spawn,
 };
 
+/// Most characters in the main routine or a movement function, newline
+/// excluded.
+const MAX_ROUTINE_LEN: usize = 20;
+
+/// Movement functions the robot can be given.
+const FUNCTIONS: usize = 3;
+
 pub struct Problem;
 
 impl Solver for Problem {
@@ -25,22 +31,7 @@ impl Solver for Problem {
     }
 
     fn solve_first(&self, input: &Self::Input) -> Self::Output1 {
-        let (io, tx, rx) = AsyncIO::new();
-        let mut computer = IntCodeComputer::new(input.to_vec(), io);
-
-        let t = spawn(move || computer.run());
-
-        let s = String::from_utf8(
-            from_fn(|| rx.recv().ok())
-                .map(|v| v as u8)
-                .collect::<Vec<_>>(),
-        )
-        .unwrap();
-
-        drop(tx);
-        let _ = t.join();
-
-        let grid: Grid<Cell> = Grid::from_str(&s).unwrap();
+        let grid = camera_view(input);
 
         println!("{}", grid);
 
@@ -52,8 +43,150 @@ impl Solver for Problem {
     }
 
     fn solve_second(&self, input: &Self::Input) -> Self::Output2 {
-        0
+        let path = path(&camera_view(input));
+        let (main, functions) = compress(&path).expect("path does not fit the movement functions");
+
+        let mut program = input.to_vec();
+        program[0] = 2; // wake the robot up
+        let mut io = AsciiIO::new();
+        io.push_line(&main);
+        for function in functions.iter() {
+            io.push_line(function);
+        }
+        io.push_line("n"); // no continuous video feed
+
+        let mut computer = IntCodeComputer::new(program, io);
+        if let Err(e) = computer.run() {
+            panic!("ASCII program failed: {}", e);
+        }
+        // the robot prints the map and its prompts before the dust collected
+        let lines = iter::from_fn(|| computer.io.next_line()).collect::<Vec<_>>();
+        match computer.io.next_value() {
+            Some(dust) => dust as u64,
+            None => panic!("robot did not report the dust:\n{}", lines.join("\n")),
+        }
+    }
+}
+
+fn camera_view(program: &[i64]) -> Grid<Cell> {
+    let mut computer = IntCodeComputer::new(program.to_vec(), AsciiIO::new());
+    if let Err(e) = computer.run() {
+        panic!("ASCII program failed: {}", e);
+    }
+
+    let s = computer.io.drain_lines().join("\n");
+    Grid::from_str(&s).unwrap()
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Move {
+    turn: char,
+    steps: usize,
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{},{}", self.turn, self.steps)
+    }
+}
+
+/// Moves following the scaffold from the robot to its end, turning only at
+/// corners.
+fn path(grid: &Grid<Cell>) -> Vec<Move> {
+    let (mut pos, mut dir) = (0..grid.w)
+        .cartesian_product(0..grid.h)
+        .find_map(|(x, y)| match grid.get((x, y)) {
+            Some(Cell::Bot(dir)) => Some(((x as isize, y as isize), dir.clone())),
+            _ => None,
+        })
+        .expect("no robot on the map");
+    let is_scaffold = |(x, y): (isize, isize)| {
+        x >= 0
+            && y >= 0
+            && (x as usize) < grid.w
+            && (y as usize) < grid.h
+            && grid.get((x as usize, y as usize)) == Some(&Cell::Wall)
+    };
+
+    let mut moves = vec![];
+    loop {
+        let turn = if is_scaffold(dir.left().ahead(pos)) {
+            dir = dir.left();
+            'L'
+        } else if is_scaffold(dir.right().ahead(pos)) {
+            dir = dir.right();
+            'R'
+        } else {
+            return moves;
+        };
+
+        let mut steps = 0;
+        while is_scaffold(dir.ahead(pos)) {
+            pos = dir.ahead(pos);
+            steps += 1;
+        }
+        moves.push(Move { turn, steps });
+    }
+}
+
+fn routine(moves: &[Move]) -> String {
+    moves.iter().join(",")
+}
+
+/// Split `path` into calls to movement functions, and return the main
+/// routine and the functions, all short enough to be given to the robot.
+fn compress(path: &[Move]) -> Option<(String, Vec<String>)> {
+    let mut functions = vec![];
+    let mut calls = vec![];
+    if !split(path, &mut functions, &mut calls) {
+        return None;
+    }
+
+    let main = calls.iter().map(|&f| char::from(b'A' + f as u8)).join(",");
+    let mut functions = functions.into_iter().map(routine).collect::<Vec<_>>();
+    // the robot asks for every function, even unused ones
+    let unused = functions.last().cloned().unwrap_or_default();
+    functions.resize(FUNCTIONS, unused);
+    Some((main, functions))
+}
+
+/// Cover `path` with calls to the functions found so far, or to new ones,
+/// backtracking when the main routine gets too long.
+fn split<'a>(path: &'a [Move], functions: &mut Vec<&'a [Move]>, calls: &mut Vec<usize>) -> bool {
+    if path.is_empty() {
+        return true;
+    }
+    // calls are written as single letters separated by commas
+    if 2 * calls.len() + 1 > MAX_ROUTINE_LEN {
+        return false;
+    }
+
+    for f in 0..functions.len() {
+        let function = functions[f];
+        if path.starts_with(function) {
+            calls.push(f);
+            if split(&path[function.len()..], functions, calls) {
+                return true;
+            }
+            calls.pop();
+        }
+    }
+
+    if functions.len() < FUNCTIONS {
+        for len in 1..=path.len() {
+            if routine(&path[..len]).len() > MAX_ROUTINE_LEN {
+                break;
+            }
+            functions.push(&path[..len]);
+            calls.push(functions.len() - 1);
+            if split(&path[len..], functions, calls) {
+                return true;
+            }
+            calls.pop();
+            functions.pop();
+        }
     }
+    false
 }
 
 fn is_intersection(grid: &Grid<Cell>, (x, y): (usize, usize)) -> bool {
@@ -80,6 +213,30 @@ enum Dir {
     W,
 }
 
+impl Dir {
+    fn left(&self) -> Self {
+        match self {
+            Dir::N => Dir::W,
+            Dir::W => Dir::S,
+            Dir::S => Dir::E,
+            Dir::E => Dir::N,
+        }
+    }
+
+    fn right(&self) -> Self {
+        self.left().left().left()
+    }
+
+    fn ahead(&self, (x, y): (isize, isize)) -> (isize, isize) {
+        match self {
+            Dir::N => (x, y - 1),
+            Dir::S => (x, y + 1),
+            Dir::E => (x + 1, y),
+            Dir::W => (x - 1, y),
+        }
+    }
+}
+
 #[derive(Clone, Eq, PartialEq)]
 enum Cell {
     Empty,
@@ -125,3 +282,46 @@ impl Display for Cell {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_path() {
+        let grid: Grid<Cell> = Grid::from_str(
+            "\
+#######...#####
+#.....#...#...#
+#.....#...#...#
+......#...#...#
+......#...###.#
+......#.....#.#
+^########...#.#
+......#.#...#.#
+......#########
+........#...#..
+....#########..
+....#...#......
+....#...#......
+....#...#......
+....#####......",
+        )
+        .unwrap();
+        let path = path(&grid);
+        assert_eq!(
+            routine(&path),
+            "R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2"
+        );
+
+        let (main, functions) = compress(&path).unwrap();
+        assert!(main.len() <= MAX_ROUTINE_LEN);
+        assert_eq!(functions.len(), FUNCTIONS);
+        assert!(functions.iter().all(|f| f.len() <= MAX_ROUTINE_LEN));
+        let expanded = main
+            .split(',')
+            .map(|call| &functions[usize::from(call.as_bytes()[0] - b'A')])
+            .join(",");
+        assert_eq!(expanded, routine(&path));
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{
+    intcode::{
+        packet::{Event, PacketNetwork},
+        parse_program,
+    },
+    solver::Solver,
+};
+use std::io::Read;
+
+/// Number of computers in the network.
+const NETWORK_SIZE: usize = 50;
+
+pub struct Problem;
+
+impl Solver for Problem {
+    type Input = Vec<i64>;
+    type Output1 = i64;
+    type Output2 = i64;
+
+    fn parse_input<R: Read>(&self, r: R) -> Self::Input {
+        parse_program(r)
+    }
+
+    fn solve_first(&self, input: &Self::Input) -> Self::Output1 {
+        let mut network = PacketNetwork::new(input, NETWORK_SIZE);
+        loop {
+            match network.next_event() {
+                Ok(Event::NatReceived(packet)) => return packet.y,
+                Ok(_) => {}
+                Err(e) => panic!("packet network failed: {}", e),
+            }
+        }
+    }
+
+    fn solve_second(&self, input: &Self::Input) -> Self::Output2 {
+        let mut network = PacketNetwork::new(input, NETWORK_SIZE);
+        let mut last_y = None;
+        loop {
+            match network.next_event() {
+                Ok(Event::NatSent(packet)) => {
+                    if last_y == Some(packet.y) {
+                        return packet.y;
+                    }
+                    last_y = Some(packet.y);
+                }
+                Ok(_) => {}
+                Err(e) => panic!("packet network failed: {}", e),
+            }
+        }
+    }
+}
@@ -5,7 +5,7 @@ use std::{
     path::Path,
 };
 
-fn input_file(day: i32) -> String {
+pub fn input_file(day: i32) -> String {
     format!("input/day{:02}", day)
 }
 